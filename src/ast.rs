@@ -0,0 +1,405 @@
+//! Abstract syntax tree
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::interner::Symbol;
+use crate::token::{Radix, StringPart, TokenKind};
+
+/// A whole parsed program: the statements of a script, in order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Program<'a> {
+    pub statements: Vec<Statement<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement<'a> {
+    // let name = value;
+    Let(LetStatement<'a>),
+
+    // return value;
+    Return(ReturnStatement<'a>),
+
+    // An expression on its own, as in `add(1, 2);`
+    Expression(Expression<'a>),
+
+    // { ... }
+    Block(BlockStatement<'a>),
+}
+
+/// A `let name = value;` statement binding `value` to `name`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LetStatement<'a> {
+    pub name: Identifier<'a>,
+    pub value: Expression<'a>,
+}
+
+/// A `return` statement, with the value it returns unless it is a bare `return;`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReturnStatement<'a> {
+    pub value: Option<Expression<'a>>,
+}
+
+/// A sequence of statements between `{` and `}`, such as the body of a function or the branch of
+/// an `if`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BlockStatement<'a> {
+    pub statements: Vec<Statement<'a>>,
+}
+
+/// A name, borrowed from the source it was parsed from like the token it came from, along with
+/// the `Symbol` the parser interned it as, so that comparing two names is a single integer
+/// comparison.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Identifier<'a> {
+    pub name: Cow<'a, str>,
+    pub symbol: Symbol,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression<'a> {
+    // x
+    Identifier(Identifier<'a>),
+
+    // 5, "five", 'f', true, null
+    Literal(Literal<'a>),
+
+    // -x or !x
+    Prefix {
+        operator: PrefixOperator,
+        right: Box<Expression<'a>>,
+    },
+
+    // x + y
+    Infix {
+        left: Box<Expression<'a>>,
+        operator: InfixOperator,
+        right: Box<Expression<'a>>,
+    },
+
+    // if (condition) { ... } else { ... }
+    If {
+        condition: Box<Expression<'a>>,
+        consequence: BlockStatement<'a>,
+        alternative: Option<BlockStatement<'a>>,
+    },
+
+    // func(x, y) { ... }
+    Function {
+        parameters: Vec<Identifier<'a>>,
+        body: BlockStatement<'a>,
+    },
+
+    // add(1, 2)
+    Call {
+        function: Box<Expression<'a>>,
+        arguments: Vec<Expression<'a>>,
+    },
+}
+
+/// A literal value, holding the same resolved values as the literal tokens it is parsed from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal<'a> {
+    Number(Cow<'a, str>),
+    Float(Cow<'a, str>),
+    RadixNumber(Radix, Cow<'a, str>),
+    String(Cow<'a, str>),
+    InterpolatedString(Vec<StringPart<'a>>),
+    Char(char),
+    Boolean(bool),
+    Null,
+}
+
+/// An operator written before its operand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrefixOperator {
+    Negate, // -
+    Not,    // !
+}
+
+/// An operator written between its two operands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InfixOperator {
+    Add,                // +
+    Subtract,           // -
+    Multiply,           // *
+    Divide,             // /
+    Remainder,          // %
+    Equals,             // ==
+    NotEquals,          // !=
+    LessThan,           // <
+    LessThanOrEqual,    // <=
+    GreaterThan,        // >
+    GreaterThanOrEqual, // >=
+    And,                // &&
+    Or,                 // ||
+}
+
+impl PrefixOperator {
+    /// Returns the prefix operator `kind` denotes, if it is one.
+    pub fn from_token(kind: &TokenKind) -> Option<Self> {
+        match kind {
+            TokenKind::Minus => Some(PrefixOperator::Negate),
+            TokenKind::Bang => Some(PrefixOperator::Not),
+            _ => None,
+        }
+    }
+}
+
+impl InfixOperator {
+    /// Returns the infix operator `kind` denotes, if it is one.
+    pub fn from_token(kind: &TokenKind) -> Option<Self> {
+        let operator = match kind {
+            TokenKind::Plus => InfixOperator::Add,
+            TokenKind::Minus => InfixOperator::Subtract,
+            TokenKind::Asterisk => InfixOperator::Multiply,
+            TokenKind::Slash => InfixOperator::Divide,
+            TokenKind::Percent => InfixOperator::Remainder,
+            TokenKind::Equals => InfixOperator::Equals,
+            TokenKind::NotEquals => InfixOperator::NotEquals,
+            TokenKind::LessThan => InfixOperator::LessThan,
+            TokenKind::LessThanOrEqual => InfixOperator::LessThanOrEqual,
+            TokenKind::GreaterThan => InfixOperator::GreaterThan,
+            TokenKind::GreaterThanOrEqual => InfixOperator::GreaterThanOrEqual,
+            TokenKind::And => InfixOperator::And,
+            TokenKind::Or => InfixOperator::Or,
+            _ => return None,
+        };
+
+        Some(operator)
+    }
+}
+
+impl fmt::Display for Program<'_> {
+    /// Prints the program back as source, one statement per line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for statement in &self.statements {
+            writeln!(f, "{statement}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Statement<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Let(statement) => write!(f, "{statement}"),
+            Statement::Return(statement) => write!(f, "{statement}"),
+            Statement::Expression(expression) => write!(f, "{expression};"),
+            Statement::Block(block) => write!(f, "{block}"),
+        }
+    }
+}
+
+impl fmt::Display for LetStatement<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "let {} = {};", self.name, self.value)
+    }
+}
+
+impl fmt::Display for ReturnStatement<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "return {value};"),
+            None => write!(f, "return;"),
+        }
+    }
+}
+
+impl fmt::Display for BlockStatement<'_> {
+    /// Prints the block on a single line, so that nested blocks don't need indenting.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("{")?;
+        for statement in &self.statements {
+            write!(f, " {statement}")?;
+        }
+
+        f.write_str(" }")
+    }
+}
+
+impl fmt::Display for Identifier<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl fmt::Display for Expression<'_> {
+    /// Prints the expression back as source. Every prefix and infix expression is wrapped in
+    /// parentheses, so the printed form shows exactly how the operators were grouped.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Identifier(identifier) => write!(f, "{identifier}"),
+            Expression::Literal(literal) => write!(f, "{literal}"),
+            Expression::Prefix { operator, right } => write!(f, "({operator}{right})"),
+            Expression::Infix {
+                left,
+                operator,
+                right,
+            } => write!(f, "({left} {operator} {right})"),
+            Expression::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                write!(f, "if {condition} {consequence}")?;
+                match alternative {
+                    Some(alternative) => write!(f, " else {alternative}"),
+                    None => Ok(()),
+                }
+            }
+            Expression::Function { parameters, body } => {
+                f.write_str("func(")?;
+                write_separated(f, parameters)?;
+                write!(f, ") {body}")
+            }
+            Expression::Call {
+                function,
+                arguments,
+            } => {
+                write!(f, "{function}(")?;
+                write_separated(f, arguments)?;
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Literal<'_> {
+    /// Prints the literal as the token it was parsed from would be printed.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let token = match self {
+            Literal::Number(text) => TokenKind::Number(Cow::Borrowed(text)),
+            Literal::Float(text) => TokenKind::Float(Cow::Borrowed(text)),
+            Literal::RadixNumber(radix, digits) => {
+                TokenKind::RadixNumber(*radix, Cow::Borrowed(digits))
+            }
+            Literal::String(text) => TokenKind::String(Cow::Borrowed(text)),
+            Literal::InterpolatedString(parts) => TokenKind::InterpolatedString(parts.clone()),
+            Literal::Char(character) => TokenKind::Char(*character),
+            Literal::Boolean(true) => TokenKind::True,
+            Literal::Boolean(false) => TokenKind::False,
+            Literal::Null => TokenKind::Null,
+        };
+
+        write!(f, "{token}")
+    }
+}
+
+impl fmt::Display for PrefixOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrefixOperator::Negate => f.write_str("-"),
+            PrefixOperator::Not => f.write_str("!"),
+        }
+    }
+}
+
+impl fmt::Display for InfixOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            InfixOperator::Add => "+",
+            InfixOperator::Subtract => "-",
+            InfixOperator::Multiply => "*",
+            InfixOperator::Divide => "/",
+            InfixOperator::Remainder => "%",
+            InfixOperator::Equals => "==",
+            InfixOperator::NotEquals => "!=",
+            InfixOperator::LessThan => "<",
+            InfixOperator::LessThanOrEqual => "<=",
+            InfixOperator::GreaterThan => ">",
+            InfixOperator::GreaterThanOrEqual => ">=",
+            InfixOperator::And => "&&",
+            InfixOperator::Or => "||",
+        };
+
+        f.write_str(symbol)
+    }
+}
+
+/// Writes `items` separated by commas, as in a parameter or argument list.
+fn write_separated<T: fmt::Display>(f: &mut fmt::Formatter<'_>, items: &[T]) -> fmt::Result {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{item}")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{
+        BlockStatement, Expression, InfixOperator, LetStatement, Literal, PrefixOperator, Program,
+        ReturnStatement, Statement,
+    };
+    use crate::test_support::identifiers;
+    use crate::token::TokenKind;
+
+    #[test]
+    fn test_display_program() {
+        // let add = func(x, y) { return x + -y; };
+        // if (add(1, 2) >= 3) { true } else { null };
+        let mut identifier = identifiers();
+        let program = Program {
+            statements: vec![
+                Statement::Let(LetStatement {
+                    name: identifier("add"),
+                    value: Expression::Function {
+                        parameters: vec![identifier("x"), identifier("y")],
+                        body: BlockStatement {
+                            statements: vec![Statement::Return(ReturnStatement {
+                                value: Some(Expression::Infix {
+                                    left: Box::new(Expression::Identifier(identifier("x"))),
+                                    operator: InfixOperator::Add,
+                                    right: Box::new(Expression::Prefix {
+                                        operator: PrefixOperator::Negate,
+                                        right: Box::new(Expression::Identifier(identifier("y"))),
+                                    }),
+                                }),
+                            })],
+                        },
+                    },
+                }),
+                Statement::Expression(Expression::If {
+                    condition: Box::new(Expression::Infix {
+                        left: Box::new(Expression::Call {
+                            function: Box::new(Expression::Identifier(identifier("add"))),
+                            arguments: vec![
+                                Expression::Literal(Literal::Number("1".into())),
+                                Expression::Literal(Literal::Number("2".into())),
+                            ],
+                        }),
+                        operator: InfixOperator::GreaterThanOrEqual,
+                        right: Box::new(Expression::Literal(Literal::Number("3".into()))),
+                    }),
+                    consequence: BlockStatement {
+                        statements: vec![Statement::Expression(Expression::Literal(
+                            Literal::Boolean(true),
+                        ))],
+                    },
+                    alternative: Some(BlockStatement {
+                        statements: vec![Statement::Expression(Expression::Literal(Literal::Null))],
+                    }),
+                }),
+            ],
+        };
+
+        let expected = "let add = func(x, y) { return (x + (-y)); };\n\
+                        if (add(1, 2) >= 3) { true; } else { null; };\n";
+        println!("ACTUAL=`{program}`, EXPECTED=`{expected}`");
+        assert!(program.to_string() == expected);
+    }
+
+    #[test]
+    fn test_operators_from_tokens() {
+        assert!(PrefixOperator::from_token(&TokenKind::Bang) == Some(PrefixOperator::Not));
+        assert!(PrefixOperator::from_token(&TokenKind::Plus).is_none());
+        assert!(InfixOperator::from_token(&TokenKind::Percent) == Some(InfixOperator::Remainder));
+        assert!(InfixOperator::from_token(&TokenKind::Or) == Some(InfixOperator::Or));
+        assert!(InfixOperator::from_token(&TokenKind::Assignment).is_none());
+    }
+}
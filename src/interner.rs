@@ -0,0 +1,102 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// A cheap handle to a name stored in an `Interner`. Two symbols from the same interner are equal
+/// exactly when the names they were interned from are equal, so comparing them is a single
+/// integer comparison no matter how long the names are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+/// A symbol table mapping each distinct name to a `Symbol` and back. Identifier tokens already
+/// borrow their names from the source, so the interner stores those borrowed slices rather than
+/// copies of them, and interning a name from the source never allocates a new string.
+#[derive(Debug, Default)]
+pub struct Interner<'a> {
+    /// The symbol handed out for each name interned so far.
+    symbols: HashMap<Cow<'a, str>, Symbol>,
+
+    /// Every name interned so far, indexed by its symbol.
+    names: Vec<Cow<'a, str>>,
+}
+
+impl<'a> Interner<'a> {
+    /// Creates a new, empty `Interner`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the symbol for `name`, interning it first if it hasn't been seen before.
+    pub fn intern(&mut self, name: impl Into<Cow<'a, str>>) -> Symbol {
+        let name = name.into();
+        if let Some(&symbol) = self.symbols.get(&name) {
+            return symbol;
+        }
+
+        let symbol = Symbol(self.names.len() as u32);
+        self.symbols.insert(name.clone(), symbol);
+        self.names.push(name);
+        symbol
+    }
+
+    /// Returns the symbol for `name` if it has already been interned, without interning it.
+    pub fn get(&self, name: &str) -> Option<Symbol> {
+        self.symbols.get(name).copied()
+    }
+
+    /// Returns the name `symbol` was interned from.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.names[symbol.0 as usize]
+    }
+
+    /// Returns the number of distinct names interned so far.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns whether nothing has been interned yet.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use crate::interner::Interner;
+    use crate::lexer::Lexer;
+    use crate::token::TokenKind;
+
+    #[test]
+    fn test_intern() {
+        let mut interner = Interner::new();
+        let x = interner.intern("x");
+        let y = interner.intern("y");
+
+        assert!(x != y);
+        assert!(interner.intern("x") == x);
+        assert!(interner.get("y") == Some(y));
+        assert!(interner.get("z").is_none());
+        assert!(interner.resolve(x) == "x");
+        assert!(interner.len() == 2);
+    }
+
+    #[test]
+    fn test_intern_identifiers() {
+        const INPUT: &str = "let total = total + count;";
+
+        let mut interner = Interner::new();
+        let symbols: Vec<_> = Lexer::new(INPUT)
+            .unwrap()
+            .filter_map(|token| match token.kind {
+                TokenKind::Identifier(Cow::Borrowed(name)) => Some(interner.intern(name)),
+                _ => None,
+            })
+            .collect();
+
+        assert!(symbols.len() == 3);
+        assert!(symbols[0] == symbols[1]);
+        assert!(symbols[0] != symbols[2]);
+        assert!(interner.resolve(symbols[2]) == "count");
+    }
+}
@@ -0,0 +1,110 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use crate::token::TokenKind;
+
+/// The set of words the `Lexer` treats as keywords rather than identifiers. The default table is
+/// the language's own keywords; embedders using the language as a DSL can add words of their own
+/// or disable ones they don't want, without the lexer needing to know about either.
+#[derive(Debug, Clone, Default)]
+pub struct KeywordTable {
+    /// Words added on top of the defaults, along with the kind of token each one lexes as.
+    added: HashMap<String, TokenKind<'static>>,
+
+    /// Default keywords that have been disabled and so lex as plain identifiers.
+    disabled: HashSet<String>,
+}
+
+impl KeywordTable {
+    /// Creates a new `KeywordTable` holding the language's default keywords.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes `word` lex as `kind`, replacing its previous meaning if it had one. A word that
+    /// doesn't correspond to any built-in kind can use `TokenKind::Keyword`.
+    pub fn with_keyword(mut self, word: impl Into<String>, kind: TokenKind<'static>) -> Self {
+        let word = word.into();
+        self.disabled.remove(&word);
+        self.added.insert(word, kind);
+        self
+    }
+
+    /// Makes `word` lex as an identifier, whether it was a default keyword or an added one.
+    pub fn without_keyword(mut self, word: impl Into<String>) -> Self {
+        let word = word.into();
+        self.added.remove(&word);
+        self.disabled.insert(word);
+        self
+    }
+
+    /// Returns the kind of token `word` lexes as: its keyword, if it has one in this table, or
+    /// otherwise a `TokenKind::Identifier` borrowing it.
+    pub fn lookup<'a>(&self, word: &'a str) -> TokenKind<'a> {
+        if let Some(kind) = self.added.get(word) {
+            return kind.clone();
+        }
+
+        if self.disabled.contains(word) {
+            return TokenKind::Identifier(Cow::Borrowed(word));
+        }
+
+        TokenKind::keyword(word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keyword::KeywordTable;
+    use crate::lexer::Lexer;
+    use crate::token::TokenKind;
+
+    #[test]
+    fn test_default_keywords() {
+        let keywords = KeywordTable::new();
+
+        assert!(keywords.lookup("let") == TokenKind::Let);
+        assert!(keywords.lookup("func") == TokenKind::Function);
+        assert!(keywords.lookup("unless") == TokenKind::Identifier("unless".into()));
+    }
+
+    #[test]
+    fn test_added_and_disabled_keywords() {
+        let keywords = KeywordTable::new()
+            .with_keyword("fn", TokenKind::Function)
+            .with_keyword("unless", TokenKind::Keyword("unless".into()))
+            .without_keyword("const")
+            .without_keyword("unless")
+            .with_keyword("unless", TokenKind::Keyword("unless".into()));
+
+        assert!(keywords.lookup("fn") == TokenKind::Function);
+        assert!(keywords.lookup("func") == TokenKind::Function);
+        assert!(keywords.lookup("unless") == TokenKind::Keyword("unless".into()));
+        assert!(keywords.lookup("const") == TokenKind::Identifier("const".into()));
+    }
+
+    #[test]
+    fn test_lexer_with_keywords() {
+        const INPUT: &str = "unless let fn";
+
+        let keywords = KeywordTable::new()
+            .with_keyword("unless", TokenKind::Keyword("unless".into()))
+            .with_keyword("fn", TokenKind::Function)
+            .without_keyword("let");
+        let tokens: Vec<TokenKind> = Lexer::new(INPUT)
+            .unwrap()
+            .with_keywords(keywords)
+            .map(|token| token.kind)
+            .collect();
+
+        assert!(
+            tokens
+                == vec![
+                    TokenKind::Keyword("unless".into()),
+                    TokenKind::Identifier("let".into()),
+                    TokenKind::Function,
+                    TokenKind::EOF,
+                ]
+        );
+    }
+}
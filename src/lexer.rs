@@ -1,34 +1,215 @@
-use crate::token::Token;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::iter::FusedIterator;
+
+use crate::keyword::KeywordTable;
+use crate::span::Span;
+use crate::token::{Radix, StringPart, Token, TokenKind};
+
+/// The kind of problem a `LexError` describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LexErrorKind {
+    /// A character that can't start any token.
+    IllegalCharacter,
+
+    /// A numeric literal with stray characters, a misplaced separator or a missing exponent.
+    MalformedNumber,
+
+    /// A string literal with no closing quote before the end of the input.
+    UnterminatedString,
+
+    /// A character literal with no closing quote before the end of its line.
+    UnterminatedCharacter,
+
+    /// A character literal with nothing between its quotes.
+    EmptyCharacter,
+
+    /// A character literal holding more than one character.
+    OverlongCharacter,
+
+    /// A backslash followed by a character that doesn't form an escape sequence.
+    UnknownEscape,
+
+    /// A `\u{...}` escape that isn't one to six hex digits naming a Unicode scalar value.
+    InvalidUnicodeEscape,
+
+    /// A block comment with no closing delimiter before the end of the input.
+    UnterminatedComment,
+}
+
+impl fmt::Display for LexErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            LexErrorKind::IllegalCharacter => "illegal character",
+            LexErrorKind::MalformedNumber => "invalid numeric literal",
+            LexErrorKind::UnterminatedString => "unterminated string literal",
+            LexErrorKind::UnterminatedCharacter => "unterminated character literal",
+            LexErrorKind::EmptyCharacter => "empty character literal",
+            LexErrorKind::OverlongCharacter => "character literal holds more than one character",
+            LexErrorKind::UnknownEscape => "unknown escape sequence",
+            LexErrorKind::InvalidUnicodeEscape => "invalid unicode escape",
+            LexErrorKind::UnterminatedComment => "unterminated block comment",
+        };
+
+        f.write_str(description)
+    }
+}
+
+/// A problem found while lexing: what kind of problem it is, where it is, and the offending text.
+/// Lexing carries on past each one, so that every problem in the input can be reported at once
+/// rather than stopping at the first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    /// The kind of problem.
+    pub kind: LexErrorKind,
+
+    /// The location of the offending input.
+    pub span: Span,
+
+    /// The offending text from the input.
+    pub snippet: String,
+}
+
+impl LexError {
+    /// Returns a description of the problem quoting the offending text, without its location.
+    pub fn message(&self) -> String {
+        match self.kind {
+            // Unterminated literals run to the end of the line or input, which is too much to
+            // quote, and an empty one has nothing worth quoting.
+            LexErrorKind::UnterminatedString
+            | LexErrorKind::UnterminatedCharacter
+            | LexErrorKind::UnterminatedComment
+            | LexErrorKind::EmptyCharacter => self.kind.to_string(),
+            LexErrorKind::OverlongCharacter => format!(
+                "character literal `{}` holds more than one character",
+                self.snippet
+            ),
+            _ => format!("{} `{}`", self.kind, self.snippet),
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Span { line, column, .. } = self.span;
+        write!(f, "{line}:{column}: {}", self.message())
+    }
+}
+
+impl Error for LexError {}
+
+/// A saved position in a `Lexer`'s token stream, returned by `Lexer::checkpoint()` so that the
+/// lexer can later be rewound to it.
+#[derive(Debug, Clone)]
+pub struct Checkpoint<'a> {
+    character: char,
+    offset: usize,
+    current: usize,
+    line: usize,
+    column: usize,
+    lookahead: VecDeque<Token<'a>>,
+    diagnostics: usize,
+    finished: bool,
+}
 
 /// Parser
-pub struct Lexer {
-    /// The raw input of the parser object.
-    input: String,
+pub struct Lexer<'a> {
+    /// The raw input of the parser object. Lexemes are borrowed from it rather than copied.
+    input: &'a str,
 
     /// The index of the current character being represented by the `character` field.
     current: usize,
 
     /// The character that is at the current position in the input string.
     character: char,
+
+    /// The byte offset of the `character` field in the input string.
+    offset: usize,
+
+    /// The one-based line of the `character` field in the input string.
+    line: usize,
+
+    /// The one-based column of the `character` field within its line, counted in characters.
+    column: usize,
+
+    /// Tokens that have been lexed ahead of time by `peek()` or `peek_n()` but not yet returned.
+    lookahead: VecDeque<Token<'a>>,
+
+    /// The problem with the token currently being lexed, if any, waiting for its span to be known.
+    problem: Option<LexErrorKind>,
+
+    /// Every problem found in the input so far.
+    diagnostics: Vec<LexError>,
+
+    /// Whether iterating over the `Lexer` has already produced its final `TokenKind::EOF`.
+    finished: bool,
+
+    /// Whether `tokenize()` returns comments as `TokenKind::Comment` or `TokenKind::BlockComment`
+    /// instead of skipping them.
+    comments: bool,
+
+    /// The words that lex as keywords rather than identifiers.
+    keywords: KeywordTable,
 }
 
-impl Lexer {
-    /// Creates a new `Parser` object with the provided `String` input.
-    pub fn new(input: String) -> Option<Self> {
-        if let Some(character) = input.chars().nth(0) {
-            Some(Self {
-                input,
-                current: 0,
-                character,
-            })
-        } else {
-            None
-        }
+impl<'a> Lexer<'a> {
+    /// Creates a new `Parser` object that borrows the provided `&str` input.
+    pub fn new(input: &'a str) -> Option<Self> {
+        let character = input.chars().next()?;
+
+        Some(Self {
+            input,
+            current: 0,
+            character,
+            offset: 0,
+            line: 1,
+            column: 1,
+            lookahead: VecDeque::new(),
+            problem: None,
+            diagnostics: Vec::new(),
+            finished: false,
+            comments: false,
+            keywords: KeywordTable::new(),
+        })
+    }
+
+    /// Makes the `Lexer` return comments as `TokenKind::Comment` and `TokenKind::BlockComment`
+    /// rather than discarding them, for tools such as a formatter that need to keep them.
+    pub fn with_comments(mut self) -> Self {
+        self.comments = true;
+        self
+    }
+
+    /// Makes the `Lexer` recognise the keywords in `keywords` instead of the default ones.
+    pub fn with_keywords(mut self, keywords: KeywordTable) -> Self {
+        self.keywords = keywords;
+        self
+    }
+
+    /// Returns every problem found in the input that has been lexed so far. Lexing carries on past
+    /// each one, producing a `TokenKind::Error` in its place, so once the input is exhausted this
+    /// holds all of them.
+    pub fn diagnostics(&self) -> &[LexError] {
+        &self.diagnostics
+    }
+
+    /// Returns the unread remainder of the input, starting at the current character. All
+    /// lookahead goes through here, so it only ever walks the few characters it needs.
+    fn rest(&self) -> &'a str {
+        &self.input[self.offset..]
+    }
+
+    /// Returns the slice of the input between the `start` and `end` byte offsets.
+    fn slice(&self, start: usize, end: usize) -> &'a str {
+        &self.input[start..end]
     }
 
     /// Returns the current read position of the calling `Lexer`.
     pub fn current(&self) -> Option<usize> {
-        if self.current == (self.input.len() - 1) {
+        if self.offset + self.character.len_utf8() == self.input.len() {
             return None;
         }
 
@@ -37,12 +218,12 @@ impl Lexer {
 
     /// Returns the current character of the calling `Lexer`.
     pub fn current_char(&self) -> Option<char> {
-        self.input.chars().nth(self.current)
+        self.rest().chars().next()
     }
 
     /// Returns the next read position of the calling `Lexer`.
-    pub fn next(&self) -> Option<usize> {
-        if (self.current + 1) > self.input.len() {
+    pub fn next_position(&self) -> Option<usize> {
+        if self.offset >= self.input.len() {
             return None;
         }
 
@@ -51,154 +232,733 @@ impl Lexer {
 
     /// Returns the next character of the calling `Lexer`.
     pub fn next_char(&self) -> Option<char> {
-        self.input.chars().nth(self.current + 1)
+        self.peek_char(1)
+    }
+
+    /// Returns the character `offset` characters ahead of the current one, for the few places
+    /// that need more than one character of lookahead.
+    fn peek_char(&self, offset: usize) -> Option<char> {
+        self.rest().chars().nth(offset)
     }
 
-    /// Advances the current position of the parser object by one.
-    /// If no valid token has been found, then set the character field to the null bit, '\0'.
+    /// Returns whether the whole input has been read. The end is found by position rather than by
+    /// the `character` field, so that a NUL character in the input isn't mistaken for it.
+    fn at_end(&self) -> bool {
+        self.offset >= self.input.len()
+    }
+
+    /// Advances the current position of the parser object by one, keeping the line and column in
+    /// step with it. Once the input is exhausted the character field holds '\0' as a placeholder,
+    /// and `at_end()` is what tells the end apart. This only ever decodes the one character being
+    /// moved onto, so lexing the whole input is linear in its length.
     pub fn advance(&mut self) {
-        let new_current = self.current + 1;
-        if new_current > self.input.len() {
+        if self.at_end() {
             return;
         }
 
-        let new_char = match self.input.chars().nth(new_current) {
-            Some(c) => c,
-            None => '\0',
-        };
+        if self.character == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        self.offset += self.character.len_utf8();
 
-        self.current = new_current;
-        self.character = new_char;
+        self.current += 1;
+        self.character = self.current_char().unwrap_or('\0');
     }
 
-    /// Traverses the source input until a non-whitespace character is found.
-    fn skip_whitespace(&mut self) -> String {
-        let start = self.current;
-        loop {
-            let c = self
-                .current_char()
-                .expect("Expected a valid whitespace character.");
-            match c {
-                ' ' | '\t' | '\n' | '\r' => self.advance(),
-                _ => break,
-            }
+    /// Consumes the next character if it is `expected`, returning whether it did. This is the one
+    /// character of lookahead behind every two-character operator.
+    fn match_next(&mut self, expected: char) -> bool {
+        if self.next_char() != Some(expected) {
+            return false;
         }
-        let end = self.current;
 
-        self.input[start..end].to_string()
+        self.advance();
+        true
+    }
+
+    /// Advances while the current character satisfies `predicate`, and returns the range of input
+    /// that was consumed. Every multi-character lexeme is extracted through here, so the lexer
+    /// only ever hands out slices of its input rather than building up text of its own.
+    fn read_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let start = self.offset;
+        while !self.at_end() && predicate(self.character) {
+            self.advance();
+        }
+        let end = self.offset;
+
+        self.slice(start, end)
+    }
+
+    /// Traverses the source input until a non-whitespace character is found.
+    fn skip_whitespace(&mut self) -> &'a str {
+        self.read_while(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
     }
 
     /// Converts the current character into a token if the underlying character is valid.
-    /// This is the public interface to the `lex()` function.
-    pub fn tokenize(&mut self) -> Option<Token> {
-        if self.character == '\0' {
-            return None;
+    /// This is the public interface to the `lex()` function, and skips over any whitespace, as
+    /// well as comments unless the `Lexer` was created `with_comments()`. Each token comes back
+    /// with the `Span` of input it was lexed from. Once the input is exhausted this returns
+    /// `TokenKind::EOF`, and keeps returning it on every further call.
+    pub fn tokenize(&mut self) -> Token<'a> {
+        self.lookahead.pop_front().unwrap_or_else(|| self.scan())
+    }
+
+    /// Returns the token the next call to `tokenize()` will return, without consuming it.
+    pub fn peek(&mut self) -> &Token<'a> {
+        self.peek_n(0)
+    }
+
+    /// Returns the token `k` tokens ahead without consuming anything, where `peek_n(0)` is the
+    /// same as `peek()`. Looking past the end of the input gives `TokenKind::EOF`.
+    pub fn peek_n(&mut self, k: usize) -> &Token<'a> {
+        while self.lookahead.len() <= k {
+            let token = self.scan();
+            self.lookahead.push_back(token);
+        }
+
+        &self.lookahead[k]
+    }
+
+    /// Returns whether every token has been consumed, so that the next call to `tokenize()` will
+    /// return `TokenKind::EOF`. Trailing whitespace and skipped comments count as consumed.
+    pub fn is_at_end(&mut self) -> bool {
+        self.peek().kind == TokenKind::EOF
+    }
+
+    /// Saves the current position in the token stream, so that the parser can speculatively read
+    /// ahead and then `rewind()` to it if that doesn't work out.
+    pub fn checkpoint(&self) -> Checkpoint<'a> {
+        Checkpoint {
+            character: self.character,
+            offset: self.offset,
+            current: self.current,
+            line: self.line,
+            column: self.column,
+            lookahead: self.lookahead.clone(),
+            diagnostics: self.diagnostics.len(),
+            finished: self.finished,
+        }
+    }
+
+    /// Restores the position saved by `checkpoint()`, so the tokens read since then are returned
+    /// again. Any diagnostics found since then are dropped, since they will be found again.
+    pub fn rewind(&mut self, checkpoint: Checkpoint<'a>) {
+        self.character = checkpoint.character;
+        self.offset = checkpoint.offset;
+        self.current = checkpoint.current;
+        self.line = checkpoint.line;
+        self.column = checkpoint.column;
+        self.lookahead = checkpoint.lookahead;
+        self.diagnostics.truncate(checkpoint.diagnostics);
+        self.finished = checkpoint.finished;
+    }
+
+    /// Lexes the next token straight from the input, bypassing the lookahead buffer.
+    fn scan(&mut self) -> Token<'a> {
+        while !self.at_end() {
+            let (start, line, column) = (self.offset, self.line, self.column);
+            let kind = self.lex();
+            let span = Span {
+                start,
+                end: self.offset,
+                line,
+                column,
+            };
+
+            if let Some(kind) = self.problem.take() {
+                let snippet = self.slice(start, self.offset).to_string();
+                self.diagnostics.push(LexError {
+                    kind,
+                    span,
+                    snippet,
+                });
+            }
+
+            match kind {
+                TokenKind::Whitespace(_) => continue,
+                TokenKind::Comment(_) | TokenKind::BlockComment(_) if !self.comments => continue,
+                kind => {
+                    let lexeme = Cow::Borrowed(self.slice(start, self.offset));
+                    return Token { kind, lexeme, span };
+                }
+            }
+        }
+
+        Token {
+            kind: TokenKind::EOF,
+            lexeme: Cow::Borrowed(""),
+            span: Span {
+                start: self.offset,
+                end: self.offset,
+                line: self.line,
+                column: self.column,
+            },
         }
+    }
 
-        Some(self.lex())
+    /// Lexes the whole of the remaining input, returning every token up to and including the final
+    /// `TokenKind::EOF`, or the first `LexError` found if any of the input couldn't be lexed.
+    pub fn tokenize_all(&mut self) -> Result<Vec<Token<'a>>, LexError> {
+        let tokens: Vec<Token<'a>> = self.by_ref().collect();
+
+        match self.diagnostics.first() {
+            Some(error) => Err(error.clone()),
+            None => Ok(tokens),
+        }
     }
 
     /// Attempts to read consecutive ASCII characters until a whitespace is encountered.
     /// This lexer method is typically used to tokenize symbols or identifiers,
     /// such as those in variables, function names, class names, trait names, etc.
-    fn read_identifier(&mut self) -> String {
-        let start = self.current;
-        while self.character.is_ascii_alphabetic() {
+    fn read_identifier(&mut self) -> &'a str {
+        self.read_while(|c| c.is_ascii_alphabetic())
+    }
+
+    /// Reads a `//` comment up to, but not including, the end of its line, and returns the text
+    /// following the slashes.
+    fn read_comment(&mut self) -> &'a str {
+        self.advance();
+        self.advance();
+
+        self.read_while(|c| c != '\n')
+    }
+
+    /// Reads a `/* */` comment, including any comments nested inside it, and returns the text
+    /// between the outermost delimiters. Returns `None` if the input ends before every comment
+    /// has been closed.
+    fn read_block_comment(&mut self) -> Option<&'a str> {
+        self.advance();
+        self.advance();
+
+        let start = self.offset;
+        let mut depth = 1;
+        while !self.at_end() {
+            match (self.character, self.next_char()) {
+                ('/', Some('*')) => {
+                    depth += 1;
+                    self.advance();
+                }
+                ('*', Some('/')) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let comment = self.slice(start, self.offset);
+                        self.advance();
+                        self.advance();
+                        return Some(comment);
+                    }
+                    self.advance();
+                }
+                _ => {}
+            }
+            self.advance();
+        }
+
+        None
+    }
+
+    /// Lexes a `"` string literal, resolving escape sequences as it goes. Text between `{` and `}`
+    /// is an embedded expression, which turns the literal into a
+    /// `TokenKind::InterpolatedString` whose parts alternate between text and the source of each
+    /// expression. Literal braces are written `\{` and `\}`.
+    fn lex_string(&mut self) -> TokenKind<'a> {
+        self.advance();
+
+        let mut parts = Vec::new();
+        let mut text = Cow::Borrowed("");
+        let mut run = self.offset;
+        let mut bad_escape = None;
+        loop {
+            match self.character {
+                '"' => {
+                    append(&mut text, self.slice(run, self.offset));
+                    self.advance();
+                    break;
+                }
+                '\\' => {
+                    append(&mut text, self.slice(run, self.offset));
+                    bad_escape = bad_escape.or(self.read_escape(&mut text));
+                    run = self.offset;
+                }
+                '{' => {
+                    append(&mut text, self.slice(run, self.offset));
+                    if !text.is_empty() {
+                        parts.push(StringPart::Text(std::mem::take(&mut text)));
+                    }
+
+                    match self.read_interpolation() {
+                        Some(expression) => parts.push(StringPart::Expression(expression.into())),
+                        None => return self.unterminated_string(),
+                    }
+                    run = self.offset;
+                }
+                _ if self.at_end() => return self.unterminated_string(),
+                _ => self.advance(),
+            }
+        }
+
+        // A bad escape has already been reported, but the string is still read to its end so
+        // that lexing carries on after it.
+        if let Some(kind) = bad_escape {
+            return TokenKind::Error(kind);
+        }
+
+        if parts.is_empty() {
+            return TokenKind::String(text);
+        }
+
+        if !text.is_empty() {
+            parts.push(StringPart::Text(text));
+        }
+        TokenKind::InterpolatedString(parts)
+    }
+
+    /// Reads the escape sequence at the current backslash and appends the character it stands for
+    /// to `text`. A bad escape is reported with a span covering just that escape, and returned so
+    /// the literal holding it becomes an error token. An escape such as `\u{41}` takes its braces
+    /// with it, so they are never read as an interpolation.
+    fn read_escape(&mut self, text: &mut Cow<'a, str>) -> Option<LexErrorKind> {
+        let (start, line, column) = (self.offset, self.line, self.column);
+        self.advance();
+
+        let escaped = match self.character {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' | '"' | '\'' | '{' | '}' => Some(self.character),
+            _ => None,
+        };
+        if let Some(escaped) = escaped {
+            text.to_mut().push(escaped);
+            self.advance();
+            return None;
+        }
+
+        let kind = if self.character == 'u' {
+            self.advance();
+            match self.read_braced().and_then(unicode_scalar) {
+                Some(escaped) => {
+                    text.to_mut().push(escaped);
+                    return None;
+                }
+                None => LexErrorKind::InvalidUnicodeEscape,
+            }
+        } else {
+            self.advance();
+            self.read_braced();
+            LexErrorKind::UnknownEscape
+        };
+
+        self.diagnostics.push(LexError {
+            kind,
+            span: Span {
+                start,
+                end: self.offset,
+                line,
+                column,
+            },
+            snippet: self.slice(start, self.offset).to_string(),
+        });
+
+        Some(kind)
+    }
+
+    /// Reads the braces following the letter of an escape sequence, if there are any, and returns
+    /// the text between them. The braces can't run past a quote or the end of the line, so a
+    /// missing `}` doesn't swallow the rest of the literal.
+    fn read_braced(&mut self) -> Option<&'a str> {
+        if self.character != '{' {
+            return None;
+        }
+        self.advance();
+
+        let inner = self.read_while(|c| !matches!(c, '}' | '"' | '\'' | '\n'));
+        if self.character != '}' {
+            return None;
+        }
+        self.advance();
+
+        Some(inner)
+    }
+
+    /// Lexes a `'` character literal, which must hold exactly one character or escape sequence.
+    fn lex_char(&mut self) -> TokenKind<'a> {
+        self.advance();
+
+        let mut text = Cow::Borrowed("");
+        let mut run = self.offset;
+        let mut bad_escape = None;
+        loop {
+            match self.character {
+                '\'' => {
+                    append(&mut text, self.slice(run, self.offset));
+                    self.advance();
+                    break;
+                }
+                '\\' => {
+                    append(&mut text, self.slice(run, self.offset));
+                    bad_escape = bad_escape.or(self.read_escape(&mut text));
+                    run = self.offset;
+                }
+                // Character literals can't span lines, so a newline means the closing quote is
+                // missing rather than part of the literal.
+                _ if self.character == '\n' || self.at_end() => {
+                    return self.error(LexErrorKind::UnterminatedCharacter);
+                }
+                _ => self.advance(),
+            }
+        }
+
+        // A bad escape has already been reported, and stands for no character to count.
+        if let Some(kind) = bad_escape {
+            return TokenKind::Error(kind);
+        }
+
+        let mut characters = text.chars();
+        match (characters.next(), characters.next()) {
+            (Some(character), None) => TokenKind::Char(character),
+            (None, _) => self.error(LexErrorKind::EmptyCharacter),
+            _ => self.error(LexErrorKind::OverlongCharacter),
+        }
+    }
+
+    /// Reads an expression embedded in a string literal, from the current `{` up to its matching
+    /// `}`, and returns the source between them. Braces, and string and character literals nested
+    /// inside the expression, are skipped over whole. Returns `None` if the input ends first.
+    fn read_interpolation(&mut self) -> Option<&'a str> {
+        self.advance();
+
+        let start = self.offset;
+        let mut depth = 1;
+        loop {
+            match self.character {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let expression = self.slice(start, self.offset);
+                        self.advance();
+                        return Some(expression);
+                    }
+                }
+                quote @ ('"' | '\'') => {
+                    self.advance();
+                    while !self.at_end() && self.character != quote {
+                        if self.character == '\\' {
+                            self.advance();
+                        }
+                        self.advance();
+                    }
+                }
+                _ => {}
+            }
+
+            if self.at_end() {
+                return None;
+            }
+            self.advance();
+        }
+    }
+
+    /// Records `kind` as the problem with the token being lexed, which `scan()` reports once the
+    /// token's span is known, and returns the `TokenKind::Error` standing in for the token.
+    fn error(&mut self, kind: LexErrorKind) -> TokenKind<'a> {
+        self.problem = Some(kind);
+        TokenKind::Error(kind)
+    }
+
+    /// Consumes the rest of a string literal that was never closed, which can only happen at the
+    /// end of the input.
+    fn unterminated_string(&mut self) -> TokenKind<'a> {
+        while !self.at_end() {
             self.advance();
         }
-        let end = self.current;
 
-        self.input[start..end].to_string()
+        self.error(LexErrorKind::UnterminatedString)
     }
 
     /// Attempts to read consecutive ASCII digits until a non-ASCII digit is enountered.
-    /// This is the primary lexer method for tokenizing numerical values.
-    fn read_number(&mut self) -> String {
-        let start = self.current;
-        while self.character.is_ascii_digit() {
+    /// This is the primary lexer method for tokenizing numerical values. Underscores are read as
+    /// part of the digits, and their placement is checked once the whole literal is known.
+    fn read_number(&mut self) -> &'a str {
+        self.read_while(|c| c.is_ascii_digit() || c == '_')
+    }
+
+    /// Consumes the rest of a malformed numeric literal, so the whole lexeme is reported as a
+    /// single `TokenKind::Error` rather than a string of odd tokens.
+    fn malformed_number(&mut self) -> TokenKind<'a> {
+        self.read_while(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_');
+
+        self.error(LexErrorKind::MalformedNumber)
+    }
+
+    /// Lexes a numeric literal starting at the current digit: either an integer, or a float with a
+    /// fractional part and/or an exponent, such as `3.14`, `1e10` or `2.5e-3`. A fractional part
+    /// needs a digit after the `.`, so `1.` is an integer followed by a dot.
+    fn lex_number(&mut self) -> TokenKind<'a> {
+        let start = self.offset;
+
+        let radix = match (self.character, self.next_char()) {
+            ('0', Some('x')) => Some(Radix::Hexadecimal),
+            ('0', Some('o')) => Some(Radix::Octal),
+            ('0', Some('b')) => Some(Radix::Binary),
+            _ => None,
+        };
+        if let Some(radix) = radix {
+            return self.lex_radix_number(radix);
+        }
+
+        let mut float = false;
+        self.read_number();
+
+        if self.character == '.' && self.next_char().is_some_and(|c| c.is_ascii_digit()) {
+            float = true;
             self.advance();
+            self.read_number();
+        }
+
+        if matches!(self.character, 'e' | 'E') {
+            let sign = usize::from(matches!(self.next_char(), Some('+' | '-')));
+            if self.peek_char(1 + sign).is_some_and(|c| c.is_ascii_digit()) {
+                float = true;
+                for _ in 0..=sign {
+                    self.advance();
+                }
+                self.read_number();
+            }
+        }
+
+        // Letters running straight on from the literal, as in `123abc` or `1e`, or a second
+        // fractional part, as in `1.2.3`, make the whole lexeme a malformed literal rather than
+        // a number followed by something else.
+        let extra_fraction =
+            self.character == '.' && self.next_char().is_some_and(|c| c.is_ascii_digit());
+        let literal = self.slice(start, self.offset);
+        if self.character.is_ascii_alphabetic()
+            || extra_fraction
+            || !has_valid_separators(literal, 10)
+        {
+            return self.malformed_number();
         }
-        let end = self.current;
 
-        println!("{:?}", self.input[start..end].to_string());
-        self.input[start..end].to_string()
+        let literal = strip_separators(literal);
+        if float {
+            TokenKind::Float(literal)
+        } else {
+            TokenKind::Number(literal)
+        }
+    }
+
+    /// Lexes the digits of an integer literal written with a radix prefix, such as `0x1F`, after
+    /// `lex_number()` has recognised the prefix. The prefix isn't kept in the
+    /// token, since the `Radix` already says how to convert the digits.
+    fn lex_radix_number(&mut self, radix: Radix) -> TokenKind<'a> {
+        self.advance();
+        self.advance();
+        let digits = self.read_while(|c| c.is_digit(radix.base()) || c == '_');
+
+        // A prefix with no digits, or characters that aren't digits in the radix, as in `0b102`
+        // or `0x1G`, make the whole lexeme malformed.
+        if digits.is_empty()
+            || self.character.is_ascii_alphanumeric()
+            || !has_valid_separators(digits, radix.base())
+        {
+            return self.malformed_number();
+        }
+
+        TokenKind::RadixNumber(radix, strip_separators(digits))
     }
 
     /// The main lexing method of the `Lexer` object. It will translate the current character into
     /// a `TokenType` variant.
-    fn lex(&mut self) -> Token {
-        // Check if the current character is a whitespace character, and skip until a non-whitespace
-        // character is reached.
-        let mut literal = self.character.to_string();
+    fn lex(&mut self) -> TokenKind<'a> {
         let token_type = match self.character {
-            // Whitespace characters
-            ' ' | '\t' | '\n' => {
+            // Whitespace characters. The multi-character arms leave the lexer positioned on the
+            // character following the lexeme, so they return without the final advance.
+            ' ' | '\t' | '\n' | '\r' => {
                 let skipped = self.skip_whitespace();
-                Token::Whitespace(skipped)
+                return TokenKind::Whitespace(Cow::Borrowed(skipped));
             }
 
             // Alphabetical ASCII characters
             'a'..='z' | 'A'..='Z' => {
-                // todo!("Implement `Character` tokenization");
                 let identifier = self.read_identifier();
-                Token::Identifier(identifier)
+                return self.keywords.lookup(identifier);
             }
 
             // Numerical characters
-            '0'..'9' => {
-                todo!("Implement `Number` tokenization");
+            '0'..='9' => return self.lex_number(),
+
+            // A leading digit separator, as in `_100`, is a malformed number.
+            '_' if self.next_char().is_some_and(|c| c.is_ascii_digit()) => {
+                return self.malformed_number();
             }
 
+            // String literals
+            '"' => return self.lex_string(),
+
+            // Character literals
+            '\'' => return self.lex_char(),
+
             // Separators
-            '(' => Token::LeftParen,
-            ')' => Token::RightParen,
-            '{' => Token::LeftBrace,
-            '}' => Token::RightBrace,
-            '[' => Token::LeftBracket,
-            ']' => Token::RightBracket,
-
-            // Operators
-            '*' => Token::Asterisk,
-            '>' => Token::GreaterThan,
-            '<' => Token::LessThan,
-            '-' => Token::Minus,
-            '%' => Token::Percent,
-            '+' => Token::Plus,
-            '/' => Token::Slash,
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            '{' => TokenKind::LeftBrace,
+            '}' => TokenKind::RightBrace,
+            '[' => TokenKind::LeftBracket,
+            ']' => TokenKind::RightBracket,
+
+            // Operators, each of which may be followed by '=' to form a compound assignment
+            '*' => {
+                if self.match_next('=') {
+                    TokenKind::AsteriskAssignment
+                } else {
+                    TokenKind::Asterisk
+                }
+            }
+            '>' => {
+                if self.match_next('=') {
+                    TokenKind::GreaterThanOrEqual
+                } else {
+                    TokenKind::GreaterThan
+                }
+            }
+            '<' => {
+                if self.match_next('=') {
+                    TokenKind::LessThanOrEqual
+                } else {
+                    TokenKind::LessThan
+                }
+            }
+            '-' => {
+                if self.match_next('>') {
+                    TokenKind::Arrow
+                } else if self.match_next('=') {
+                    TokenKind::MinusAssignment
+                } else {
+                    TokenKind::Minus
+                }
+            }
+            '%' => {
+                if self.match_next('=') {
+                    TokenKind::PercentAssignment
+                } else {
+                    TokenKind::Percent
+                }
+            }
+            '+' => {
+                if self.match_next('=') {
+                    TokenKind::PlusAssignment
+                } else {
+                    TokenKind::Plus
+                }
+            }
+            '/' => {
+                // Two slashes start a comment that runs to the end of the line, which is
+                // multi-character and so returns early like the other lexeme arms. Exactly three
+                // slashes make it a doc comment, which is always kept for the declaration that
+                // follows it, while four or more are an ordinary comment.
+                if self.next_char() == Some('/') {
+                    let comment = self.read_comment();
+                    if let Some(doc) = comment
+                        .strip_prefix('/')
+                        .filter(|doc| !doc.starts_with('/'))
+                    {
+                        return TokenKind::DocComment(Cow::Borrowed(doc));
+                    }
+                    return TokenKind::Comment(Cow::Borrowed(comment));
+                }
+
+                // A slash and an asterisk open a block comment, which may nest and so needs
+                // its own scanning routine rather than a single character of lookahead.
+                if self.next_char() == Some('*') {
+                    return match self.read_block_comment() {
+                        Some(comment) => TokenKind::BlockComment(Cow::Borrowed(comment)),
+                        None => self.error(LexErrorKind::UnterminatedComment),
+                    };
+                }
+
+                if self.match_next('=') {
+                    TokenKind::SlashAssignment
+                } else {
+                    TokenKind::Slash
+                }
+            }
 
             // Equality Operators
             '=' => {
                 // If the next character is an equals '=', then the intended symbol
-                // should be an equality operation, '=='. Otherwise, it's just an
-                // assignment operation.
-                todo!("Implement `Equality` and `Assignment` tokenization");
+                // should be an equality operation, '=='. A following '>' makes a fat
+                // arrow, '=>'. Otherwise, it's just an assignment operation.
+                if self.match_next('=') {
+                    TokenKind::Equals
+                } else if self.match_next('>') {
+                    TokenKind::FatArrow
+                } else {
+                    TokenKind::Assignment
+                }
             }
             '!' => {
                 // If the next character is an equals '=', then the intended symbol
                 // should be an non-equality operation, '!='. Otherwise, it's just a
                 // normal bang symbol.
-                todo!("Implement `Negated-Equality (or NotEquals)` tokenization")
+                if self.match_next('=') {
+                    TokenKind::NotEquals
+                } else {
+                    TokenKind::Bang
+                }
             }
 
-            // Unused Symbols
-            '&' => Token::Ampersand,
-            '@' => Token::Asperand,
-            '^' => Token::Carrot,
-            '$' => Token::Dollar,
-            '#' => Token::Pound,
-            '~' => Token::Tilde,
+            // Logical Operators, which share their first character with an unused symbol.
+            '&' => {
+                if self.match_next('&') {
+                    TokenKind::And
+                } else {
+                    TokenKind::Ampersand
+                }
+            }
+            '|' => {
+                if self.match_next('|') {
+                    TokenKind::Or
+                } else {
+                    TokenKind::Pipe
+                }
+            }
 
-            // Other Characters
-            ';' => Token::Semicolon,
-            ',' => Token::Comma,
+            // Unused Symbols
+            '@' => TokenKind::Asperand,
+            '^' => TokenKind::Carrot,
+            '$' => TokenKind::Dollar,
+            '#' => TokenKind::Pound,
+            '~' => TokenKind::Tilde,
 
-            // The "end-of-file" character.
-            '\0' => Token::EOF,
+            // Member access and ranges. A dot directly between digits is part of a float literal,
+            // which `lex_number()` has already consumed by the time a dot gets here.
+            '.' => {
+                if self.match_next('.') {
+                    TokenKind::DotDot
+                } else {
+                    TokenKind::Dot
+                }
+            }
 
-            _ => {
-                println!("Encountered illegal TokenType: {}", self.character);
-                Token::Illegal(literal)
+            // Type annotations and namespaced paths
+            ':' => {
+                if self.match_next(':') {
+                    TokenKind::DoubleColon
+                } else {
+                    TokenKind::Colon
+                }
             }
+
+            // Other Characters
+            ';' => TokenKind::Semicolon,
+            ',' => TokenKind::Comma,
+
+            _ => self.error(LexErrorKind::IllegalCharacter),
         };
 
         // advance a final time
@@ -208,10 +968,82 @@ impl Lexer {
     }
 }
 
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    /// Returns the next token of the input, then a single `TokenKind::EOF` once the input has been
+    /// exhausted, after which the iteration ends.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let token = self.tokenize();
+        self.finished = token.kind == TokenKind::EOF;
+        Some(token)
+    }
+}
+
+impl FusedIterator for Lexer<'_> {}
+
+/// Appends `piece` of the input to the `text` of a string literal, only copying once the text is
+/// no longer a single borrowed run of the input.
+fn append<'a>(text: &mut Cow<'a, str>, piece: &'a str) {
+    if text.is_empty() {
+        *text = Cow::Borrowed(piece);
+    } else if !piece.is_empty() {
+        text.to_mut().push_str(piece);
+    }
+}
+
+/// Returns the character named by the hex `digits` of a `\u{...}` escape, or `None` if there are
+/// more than six of them or they name a surrogate or a value past `U+10FFFF`.
+fn unicode_scalar(digits: &str) -> Option<char> {
+    if digits.is_empty() || digits.len() > 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+}
+
+/// Returns whether every `_` digit separator in the numeric `literal` sits between two digits of
+/// the provided base, which rules out leading, trailing and doubled separators.
+fn has_valid_separators(literal: &str, base: u32) -> bool {
+    let mut previous = None;
+    let mut characters = literal.chars().peekable();
+    while let Some(c) = characters.next() {
+        if c == '_' {
+            let after_digit = previous.is_some_and(|p: char| p.is_digit(base));
+            let before_digit = characters.peek().is_some_and(|n| n.is_digit(base));
+            if !after_digit || !before_digit {
+                return false;
+            }
+        }
+        previous = Some(c);
+    }
+
+    true
+}
+
+/// Removes the digit separators from a numeric `literal`, only allocating when there are any.
+fn strip_separators(literal: &str) -> Cow<'_, str> {
+    if literal.contains('_') {
+        Cow::Owned(literal.replace('_', ""))
+    } else {
+        Cow::Borrowed(literal)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::lexer;
-    use crate::token::Token;
+    use std::borrow::Cow;
+    use std::time::Instant;
+
+    use crate::lexer::{self, LexError, LexErrorKind};
+    use crate::span::Span;
+    use crate::token::{Radix, StringPart, TokenKind};
 
     const TEST_INPUT: &str = "let five = 5;\
     let ten = 10;\
@@ -226,43 +1058,43 @@ mod tests {
 
     #[test]
     fn test_lexer_creation() {
-        if let Some(created_lexer) = lexer::Lexer::new(TEST_INPUT.to_string()) {
+        if let Some(created_lexer) = lexer::Lexer::new(TEST_INPUT) {
             assert!(created_lexer.character == 'l');
             assert!(created_lexer.current == 0);
-            assert!(created_lexer.next().unwrap() == 1);
+            assert!(created_lexer.next_position().unwrap() == 1);
         } else {
-            assert!(false);
+            panic!("Expected a lexer to be created from non-empty input.");
         }
     }
 
     #[test]
     fn test_advance() {
-        let mut test_lexer = lexer::Lexer::new(TEST_INPUT.to_string()).unwrap();
+        let mut test_lexer = lexer::Lexer::new(TEST_INPUT).unwrap();
         test_lexer.advance();
 
         assert!(test_lexer.character == 'e');
         assert!(test_lexer.current == 1);
-        assert!(test_lexer.next().unwrap() == 2);
+        assert!(test_lexer.next_position().unwrap() == 2);
     }
 
     #[test]
     fn test_lexing_of_symbols() {
-        const TEST_INPUT: &str = "~-/*&@^$#";
+        const TEST_INPUT: &str = "~-*/&@^$#";
         let expected_tokens = vec![
-            Token::Tilde,
-            Token::Minus,
-            Token::Slash,
-            Token::Asterisk,
-            Token::Ampersand,
-            Token::Asperand,
-            Token::Carrot,
-            Token::Dollar,
-            Token::Pound,
+            TokenKind::Tilde,
+            TokenKind::Minus,
+            TokenKind::Asterisk,
+            TokenKind::Slash,
+            TokenKind::Ampersand,
+            TokenKind::Asperand,
+            TokenKind::Carrot,
+            TokenKind::Dollar,
+            TokenKind::Pound,
         ];
 
-        let mut test_lexer = lexer::Lexer::new(TEST_INPUT.to_string()).unwrap();
+        let mut test_lexer = lexer::Lexer::new(TEST_INPUT).unwrap();
         for expected in expected_tokens {
-            let actual = test_lexer.tokenize().unwrap();
+            let actual = test_lexer.tokenize().kind;
             println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
             assert!(actual == expected);
         }
@@ -272,25 +1104,25 @@ mod tests {
     fn test_let_assignment() {
         const TEST_INPUT: &str = "let five = 5;\nlet ten = 10;";
 
-        let mut test_lexer = lexer::Lexer::new(TEST_INPUT.to_string()).unwrap();
+        let mut test_lexer = lexer::Lexer::new(TEST_INPUT).unwrap();
 
         let expected_tokens = vec![
             // Line 1
-            Token::Let,
-            Token::Identifier("five".to_string()),
-            Token::Assignment,
-            Token::Number("5".to_string()),
-            Token::Semicolon,
+            TokenKind::Let,
+            TokenKind::Identifier("five".into()),
+            TokenKind::Assignment,
+            TokenKind::Number("5".into()),
+            TokenKind::Semicolon,
             // Line 2
-            Token::Let,
-            Token::Identifier("ten".to_string()),
-            Token::Assignment,
-            Token::Number("10".to_string()),
-            Token::Semicolon,
+            TokenKind::Let,
+            TokenKind::Identifier("ten".into()),
+            TokenKind::Assignment,
+            TokenKind::Number("10".into()),
+            TokenKind::Semicolon,
         ];
 
         for expected in expected_tokens {
-            let actual = test_lexer.tokenize().unwrap();
+            let actual = test_lexer.tokenize().kind;
             println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
             assert!(actual == expected);
         }
@@ -302,28 +1134,28 @@ mod tests {
           return x + y;\
         }";
 
-        let mut test_lexer = lexer::Lexer::new(TEST_INPUT.to_string()).unwrap();
+        let mut test_lexer = lexer::Lexer::new(TEST_INPUT).unwrap();
         let expected_tokens = vec![
-            Token::keyword("let"),
-            Token::Identifier("add".to_string()),
-            Token::Assignment,
-            Token::Function,
-            Token::LeftParen,
-            Token::Identifier("x".to_string()),
-            Token::Comma,
-            Token::Identifier("y".to_string()),
-            Token::RightParen,
-            Token::LeftBrace,
-            Token::keyword("return"),
-            Token::Identifier("x".to_string()),
-            Token::Plus,
-            Token::Identifier("y".to_string()),
-            Token::Semicolon,
-            Token::RightBrace,
+            TokenKind::keyword("let"),
+            TokenKind::Identifier("add".into()),
+            TokenKind::Assignment,
+            TokenKind::Function,
+            TokenKind::LeftParen,
+            TokenKind::Identifier("x".into()),
+            TokenKind::Comma,
+            TokenKind::Identifier("y".into()),
+            TokenKind::RightParen,
+            TokenKind::LeftBrace,
+            TokenKind::keyword("return"),
+            TokenKind::Identifier("x".into()),
+            TokenKind::Plus,
+            TokenKind::Identifier("y".into()),
+            TokenKind::Semicolon,
+            TokenKind::RightBrace,
         ];
 
         for expected in expected_tokens {
-            let actual = test_lexer.tokenize().unwrap();
+            let actual = test_lexer.tokenize().kind;
             println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
             assert!(actual == expected);
         }
@@ -334,24 +1166,1101 @@ mod tests {
         const INPUT: &str = "10 == 10;\
             10 != 9;";
 
-        let mut test_lexer = lexer::Lexer::new(INPUT.to_string()).unwrap();
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
         let expected_tokens = vec![
             // Line 1
-            Token::Number("10".to_string()),
-            Token::Equals,
-            Token::Number("10".to_string()),
-            Token::Semicolon,
+            TokenKind::Number("10".into()),
+            TokenKind::Equals,
+            TokenKind::Number("10".into()),
+            TokenKind::Semicolon,
             // Line 2
-            Token::Number("10".to_string()),
-            Token::NotEquals,
-            Token::Number("9".to_string()),
-            Token::Semicolon,
+            TokenKind::Number("10".into()),
+            TokenKind::NotEquals,
+            TokenKind::Number("9".into()),
+            TokenKind::Semicolon,
         ];
 
         for expected in expected_tokens {
-            let actual = test_lexer.tokenize().unwrap();
+            let actual = test_lexer.tokenize().kind;
             println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
             assert!(actual == expected);
         }
     }
+
+    #[test]
+    fn test_non_ascii_input() {
+        const INPUT: &str = "naïve = 5;";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Identifier("na".into()),
+            TokenKind::Error(LexErrorKind::IllegalCharacter),
+            TokenKind::Identifier("ve".into()),
+            TokenKind::Assignment,
+            TokenKind::Number("5".into()),
+            TokenKind::Semicolon,
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_else_if_chain() {
+        const INPUT: &str = "if (x) {} else if (y) {} else {}";
+
+        // `else if` isn't a keyword of its own: it lexes as `else` followed by `if`, and the
+        // parser chains the second `if` onto the first one's alternative.
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::If,
+            TokenKind::LeftParen,
+            TokenKind::Identifier("x".into()),
+            TokenKind::RightParen,
+            TokenKind::LeftBrace,
+            TokenKind::RightBrace,
+            TokenKind::Else,
+            TokenKind::If,
+            TokenKind::LeftParen,
+            TokenKind::Identifier("y".into()),
+            TokenKind::RightParen,
+            TokenKind::LeftBrace,
+            TokenKind::RightBrace,
+            TokenKind::Else,
+            TokenKind::LeftBrace,
+            TokenKind::RightBrace,
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_control_flow_keywords() {
+        const INPUT: &str = "while for in break continue null const whiles format";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::While,
+            TokenKind::For,
+            TokenKind::In,
+            TokenKind::Break,
+            TokenKind::Continue,
+            TokenKind::Null,
+            TokenKind::Const,
+            // Keywords only match whole identifiers.
+            TokenKind::Identifier("whiles".into()),
+            TokenKind::Identifier("format".into()),
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+    }
+
+    #[test]
+    fn test_malformed_number() {
+        const INPUT: &str = "123abc 5 0x1g2;";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Error(LexErrorKind::MalformedNumber),
+            TokenKind::Number("5".into()),
+            TokenKind::Error(LexErrorKind::MalformedNumber),
+            TokenKind::Semicolon,
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+    }
+
+    #[test]
+    fn test_line_comments() {
+        const INPUT: &str = "let x = 10 / 2; // halve it\n// a whole line\nx // trailing";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Let,
+            TokenKind::Identifier("x".into()),
+            TokenKind::Assignment,
+            TokenKind::Number("10".into()),
+            TokenKind::Slash,
+            TokenKind::Number("2".into()),
+            TokenKind::Semicolon,
+            TokenKind::Identifier("x".into()),
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_line_comments_kept() {
+        const INPUT: &str = "x; // halve it\n//\ny";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap().with_comments();
+        let expected_tokens = vec![
+            TokenKind::Identifier("x".into()),
+            TokenKind::Semicolon,
+            TokenKind::Comment(" halve it".into()),
+            TokenKind::Comment("".into()),
+            TokenKind::Identifier("y".into()),
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        const INPUT: &str = "/// Adds two numbers.\n///\n//// not docs\nlet add;";
+
+        // Doc comments are kept even though ordinary comments are skipped.
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::DocComment(" Adds two numbers.".into()),
+            TokenKind::DocComment("".into()),
+            TokenKind::Let,
+            TokenKind::Identifier("add".into()),
+            TokenKind::Semicolon,
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_block_comments() {
+        const INPUT: &str = "a /* one /* two */ still one */ b /**/ c";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap().with_comments();
+        let expected_tokens = vec![
+            TokenKind::Identifier("a".into()),
+            TokenKind::BlockComment(" one /* two */ still one ".into()),
+            TokenKind::Identifier("b".into()),
+            TokenKind::BlockComment("".into()),
+            TokenKind::Identifier("c".into()),
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        const INPUT: &str = "a /* open /* inner */ b";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Identifier("a".into()),
+            TokenKind::Error(LexErrorKind::UnterminatedComment),
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_float_literals() {
+        const INPUT: &str = "3.14 0.5 1e10 2.5e-3 1E+2 7";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Float("3.14".into()),
+            TokenKind::Float("0.5".into()),
+            TokenKind::Float("1e10".into()),
+            TokenKind::Float("2.5e-3".into()),
+            TokenKind::Float("1E+2".into()),
+            TokenKind::Number("7".into()),
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_malformed_float_literals() {
+        const INPUT: &str = "1.2.3 1e 2.5e-;";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Error(LexErrorKind::MalformedNumber),
+            TokenKind::Error(LexErrorKind::MalformedNumber),
+            TokenKind::Error(LexErrorKind::MalformedNumber),
+            TokenKind::Minus,
+            TokenKind::Semicolon,
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+    }
+
+    #[test]
+    fn test_radix_literals() {
+        const INPUT: &str = "0x1F 0b1010 0o777 0 0xdeadBEEF";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::RadixNumber(Radix::Hexadecimal, "1F".into()),
+            TokenKind::RadixNumber(Radix::Binary, "1010".into()),
+            TokenKind::RadixNumber(Radix::Octal, "777".into()),
+            TokenKind::Number("0".into()),
+            TokenKind::RadixNumber(Radix::Hexadecimal, "deadBEEF".into()),
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_malformed_radix_literals() {
+        const INPUT: &str = "0b102 0o8 0x;";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Error(LexErrorKind::MalformedNumber),
+            TokenKind::Error(LexErrorKind::MalformedNumber),
+            TokenKind::Error(LexErrorKind::MalformedNumber),
+            TokenKind::Semicolon,
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+    }
+
+    #[test]
+    fn test_digit_separators() {
+        const INPUT: &str = "1_000_000 3.141_592 1e1_0 0xFF_FF 0b1010_1010";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Number("1000000".into()),
+            TokenKind::Float("3.141592".into()),
+            TokenKind::Float("1e10".into()),
+            TokenKind::RadixNumber(Radix::Hexadecimal, "FFFF".into()),
+            TokenKind::RadixNumber(Radix::Binary, "10101010".into()),
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_misplaced_digit_separators() {
+        const INPUT: &str = "_100 100_ 1__0 1_.5 0x_1F;";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Error(LexErrorKind::MalformedNumber),
+            TokenKind::Error(LexErrorKind::MalformedNumber),
+            TokenKind::Error(LexErrorKind::MalformedNumber),
+            TokenKind::Error(LexErrorKind::MalformedNumber),
+            TokenKind::Error(LexErrorKind::MalformedNumber),
+            TokenKind::Semicolon,
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+    }
+
+    #[test]
+    fn test_comparison_symbols() {
+        const INPUT: &str = "5 <= 10;\
+            10 >= 9;\
+            5 < 10 > 5;";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            // Line 1
+            TokenKind::Number("5".into()),
+            TokenKind::LessThanOrEqual,
+            TokenKind::Number("10".into()),
+            TokenKind::Semicolon,
+            // Line 2
+            TokenKind::Number("10".into()),
+            TokenKind::GreaterThanOrEqual,
+            TokenKind::Number("9".into()),
+            TokenKind::Semicolon,
+            // Line 3
+            TokenKind::Number("5".into()),
+            TokenKind::LessThan,
+            TokenKind::Number("10".into()),
+            TokenKind::GreaterThan,
+            TokenKind::Number("5".into()),
+            TokenKind::Semicolon,
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+    }
+
+    #[test]
+    fn test_bang_symbols() {
+        const INPUT: &str = "!true;\
+            10 != 9;\
+            !!x ! = y;";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            // Line 1
+            TokenKind::Bang,
+            TokenKind::True,
+            TokenKind::Semicolon,
+            // Line 2
+            TokenKind::Number("10".into()),
+            TokenKind::NotEquals,
+            TokenKind::Number("9".into()),
+            TokenKind::Semicolon,
+            // Line 3
+            TokenKind::Bang,
+            TokenKind::Bang,
+            TokenKind::Identifier("x".into()),
+            TokenKind::Bang,
+            TokenKind::Assignment,
+            TokenKind::Identifier("y".into()),
+            TokenKind::Semicolon,
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+    }
+
+    #[test]
+    fn test_logical_symbols() {
+        const INPUT: &str = "a && b || !c;\
+            a & b | c;";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            // Line 1
+            TokenKind::Identifier("a".into()),
+            TokenKind::And,
+            TokenKind::Identifier("b".into()),
+            TokenKind::Or,
+            TokenKind::Bang,
+            TokenKind::Identifier("c".into()),
+            TokenKind::Semicolon,
+            // Line 2
+            TokenKind::Identifier("a".into()),
+            TokenKind::Ampersand,
+            TokenKind::Identifier("b".into()),
+            TokenKind::Pipe,
+            TokenKind::Identifier("c".into()),
+            TokenKind::Semicolon,
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        const INPUT: &str = "x += 1; x -= 2; x *= 3; x /= 4; x %= 5; x + = 6;";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Identifier("x".into()),
+            TokenKind::PlusAssignment,
+            TokenKind::Number("1".into()),
+            TokenKind::Semicolon,
+            TokenKind::Identifier("x".into()),
+            TokenKind::MinusAssignment,
+            TokenKind::Number("2".into()),
+            TokenKind::Semicolon,
+            TokenKind::Identifier("x".into()),
+            TokenKind::AsteriskAssignment,
+            TokenKind::Number("3".into()),
+            TokenKind::Semicolon,
+            TokenKind::Identifier("x".into()),
+            TokenKind::SlashAssignment,
+            TokenKind::Number("4".into()),
+            TokenKind::Semicolon,
+            TokenKind::Identifier("x".into()),
+            TokenKind::PercentAssignment,
+            TokenKind::Number("5".into()),
+            TokenKind::Semicolon,
+            TokenKind::Identifier("x".into()),
+            TokenKind::Plus,
+            TokenKind::Assignment,
+            TokenKind::Number("6".into()),
+            TokenKind::Semicolon,
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_arrow_symbols() {
+        const INPUT: &str = "func(x) -> int; (x) => x; a - > b; a == > b;";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Function,
+            TokenKind::LeftParen,
+            TokenKind::Identifier("x".into()),
+            TokenKind::RightParen,
+            TokenKind::Arrow,
+            TokenKind::Identifier("int".into()),
+            TokenKind::Semicolon,
+            TokenKind::LeftParen,
+            TokenKind::Identifier("x".into()),
+            TokenKind::RightParen,
+            TokenKind::FatArrow,
+            TokenKind::Identifier("x".into()),
+            TokenKind::Semicolon,
+            TokenKind::Identifier("a".into()),
+            TokenKind::Minus,
+            TokenKind::GreaterThan,
+            TokenKind::Identifier("b".into()),
+            TokenKind::Semicolon,
+            TokenKind::Identifier("a".into()),
+            TokenKind::Equals,
+            TokenKind::GreaterThan,
+            TokenKind::Identifier("b".into()),
+            TokenKind::Semicolon,
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_dot_symbols() {
+        const INPUT: &str = "array.len; 1..10; 1.5; t.0; 1. .5";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Identifier("array".into()),
+            TokenKind::Dot,
+            TokenKind::Identifier("len".into()),
+            TokenKind::Semicolon,
+            TokenKind::Number("1".into()),
+            TokenKind::DotDot,
+            TokenKind::Number("10".into()),
+            TokenKind::Semicolon,
+            TokenKind::Float("1.5".into()),
+            TokenKind::Semicolon,
+            TokenKind::Identifier("t".into()),
+            TokenKind::Dot,
+            TokenKind::Number("0".into()),
+            TokenKind::Semicolon,
+            TokenKind::Number("1".into()),
+            TokenKind::Dot,
+            TokenKind::Dot,
+            TokenKind::Number("5".into()),
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_colon_symbols() {
+        const INPUT: &str = "let x: int = 5; math::max";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Let,
+            TokenKind::Identifier("x".into()),
+            TokenKind::Colon,
+            TokenKind::Identifier("int".into()),
+            TokenKind::Assignment,
+            TokenKind::Number("5".into()),
+            TokenKind::Semicolon,
+            TokenKind::Identifier("math".into()),
+            TokenKind::DoubleColon,
+            TokenKind::Identifier("max".into()),
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_token_spans() {
+        const INPUT: &str = "let x = 5;\n  naïve //\n\t!=";
+
+        // Each token with the start and end byte offsets, line and column of its span.
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_spans = vec![
+            (TokenKind::Let, 0, 3, 1, 1),
+            (TokenKind::Identifier("x".into()), 4, 5, 1, 5),
+            (TokenKind::Assignment, 6, 7, 1, 7),
+            (TokenKind::Number("5".into()), 8, 9, 1, 9),
+            (TokenKind::Semicolon, 9, 10, 1, 10),
+            (TokenKind::Identifier("na".into()), 13, 15, 2, 3),
+            (
+                TokenKind::Error(LexErrorKind::IllegalCharacter),
+                15,
+                17,
+                2,
+                5,
+            ),
+            (TokenKind::Identifier("ve".into()), 17, 19, 2, 6),
+            (TokenKind::NotEquals, 24, 26, 3, 2),
+        ];
+
+        for (token, start, end, line, column) in expected_spans {
+            let expected = Span {
+                start,
+                end,
+                line,
+                column,
+            };
+            let actual = test_lexer.tokenize();
+            println!(
+                "ACTUAL=`{:?}`, EXPECTED=`{:?}`",
+                &actual,
+                (&token, &expected)
+            );
+            assert!(actual.kind == token);
+            assert!(actual.span == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_tokens_borrow_from_input() {
+        const INPUT: &str = r#"let total = 1000 + 2.5 + 0xFF; "plain" "a\n" 1_000"#;
+
+        // Payloads and lexemes are slices of the input, and only literals whose value differs
+        // from their source text, because of escapes or digit separators, own their text.
+        for token in lexer::Lexer::new(INPUT).unwrap() {
+            println!("ACTUAL=`{token:?}`");
+            assert!(matches!(token.lexeme, Cow::Borrowed(_)));
+            match token.kind {
+                TokenKind::Identifier(text)
+                | TokenKind::Number(text)
+                | TokenKind::Float(text)
+                | TokenKind::RadixNumber(_, text)
+                | TokenKind::String(text) => {
+                    let owned = token.lexeme == "\"a\\n\"" || token.lexeme == "1_000";
+                    assert!(matches!(text, Cow::Owned(_)) == owned);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_token_lexemes() {
+        const INPUT: &str = r#"0xFF_FF 1_000 "a\n" 'b' >="#;
+
+        // Each token's lexeme is its source text as written, even where its kind has resolved it.
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            (
+                TokenKind::RadixNumber(Radix::Hexadecimal, "FFFF".into()),
+                "0xFF_FF",
+            ),
+            (TokenKind::Number("1000".into()), "1_000"),
+            (TokenKind::String("a\n".into()), r#""a\n""#),
+            (TokenKind::Char('b'), "'b'"),
+            (TokenKind::GreaterThanOrEqual, ">="),
+        ];
+
+        for (kind, lexeme) in expected_tokens {
+            let actual = test_lexer.tokenize();
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, (&kind, lexeme));
+            assert!(actual.kind == kind);
+            assert!(actual.lexeme == lexeme);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_iterator() {
+        const INPUT: &str = "let x = 5;";
+
+        let tokens: Vec<TokenKind> = lexer::Lexer::new(INPUT)
+            .unwrap()
+            .map(|token| token.kind)
+            .collect();
+        let expected_tokens = vec![
+            TokenKind::Let,
+            TokenKind::Identifier("x".into()),
+            TokenKind::Assignment,
+            TokenKind::Number("5".into()),
+            TokenKind::Semicolon,
+            TokenKind::EOF,
+        ];
+
+        println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &tokens, &expected_tokens);
+        assert!(tokens == expected_tokens);
+    }
+
+    #[test]
+    fn test_iterator_ends_after_eof() {
+        let mut test_lexer = lexer::Lexer::new("x ").unwrap();
+
+        assert!(test_lexer.next().unwrap().kind == TokenKind::Identifier("x".into()));
+        let eof = test_lexer.next().unwrap();
+        assert!(eof.kind == TokenKind::EOF);
+        assert!(eof.span.start == 2 && eof.span.is_empty());
+        assert!(test_lexer.next().is_none());
+        assert!(test_lexer.next().is_none());
+    }
+
+    #[test]
+    fn test_eof_repeats() {
+        let mut test_lexer = lexer::Lexer::new("x // trailing").unwrap();
+
+        assert!(!test_lexer.is_at_end());
+        assert!(test_lexer.tokenize().kind == TokenKind::Identifier("x".into()));
+        assert!(test_lexer.is_at_end());
+        for _ in 0..3 {
+            let eof = test_lexer.tokenize();
+            assert!(eof.kind == TokenKind::EOF);
+            assert!(eof.span.start == 13 && eof.span.is_empty());
+        }
+        assert!(test_lexer.peek_n(2).kind == TokenKind::EOF);
+        assert!(test_lexer.is_at_end());
+    }
+
+    #[test]
+    fn test_nul_characters() {
+        const INPUT: &str = "a\0b c \"x\0y\"";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let tokens: Vec<TokenKind> = test_lexer.by_ref().map(|token| token.kind).collect();
+        let expected_tokens = vec![
+            TokenKind::Identifier("a".into()),
+            TokenKind::Error(LexErrorKind::IllegalCharacter),
+            TokenKind::Identifier("b".into()),
+            TokenKind::Identifier("c".into()),
+            TokenKind::String("x\0y".into()),
+            TokenKind::EOF,
+        ];
+
+        // A NUL in the input is an ordinary character, not the end of the input.
+        println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &tokens, &expected_tokens);
+        assert!(tokens == expected_tokens);
+        assert!(test_lexer.diagnostics().len() == 1);
+        assert!(test_lexer.diagnostics()[0].span.start == 1);
+    }
+
+    #[test]
+    fn test_checkpoint_rewind() {
+        const INPUT: &str = "f(a, 1x) + g";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        assert!(test_lexer.tokenize().kind == TokenKind::Identifier("f".into()));
+        assert!(test_lexer.peek().kind == TokenKind::LeftParen);
+
+        // Read past a malformed number and the lookahead, then rewind and read it all again.
+        let checkpoint = test_lexer.checkpoint();
+        let first: Vec<_> = (0..6).map(|_| test_lexer.tokenize()).collect();
+        assert!(test_lexer.diagnostics().len() == 1);
+
+        test_lexer.rewind(checkpoint.clone());
+        assert!(test_lexer.diagnostics().is_empty());
+        let second: Vec<_> = (0..6).map(|_| test_lexer.tokenize()).collect();
+        assert!(first == second);
+        assert!(test_lexer.diagnostics().len() == 1);
+
+        test_lexer.rewind(checkpoint);
+        let rest: Vec<TokenKind> = test_lexer.map(|token| token.kind).collect();
+        assert!(rest.len() == 8);
+        assert!(rest[7] == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_tokenize_all() {
+        const INPUT: &str = "let x = 5;";
+
+        let tokens = lexer::Lexer::new(INPUT).unwrap().tokenize_all().unwrap();
+        let expected_tokens = vec![
+            TokenKind::Let,
+            TokenKind::Identifier("x".into()),
+            TokenKind::Assignment,
+            TokenKind::Number("5".into()),
+            TokenKind::Semicolon,
+            TokenKind::EOF,
+        ];
+
+        assert!(tokens.len() == expected_tokens.len());
+        for (actual, expected) in tokens.iter().zip(expected_tokens) {
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual.kind == expected);
+        }
+    }
+
+    #[test]
+    fn test_tokenize_all_error() {
+        const INPUT: &str = "let x = 5;\nlet y = 12ab;";
+
+        let error = lexer::Lexer::new(INPUT)
+            .unwrap()
+            .tokenize_all()
+            .unwrap_err();
+        let expected = LexError {
+            kind: LexErrorKind::MalformedNumber,
+            span: Span {
+                start: 19,
+                end: 23,
+                line: 2,
+                column: 9,
+            },
+            snippet: "12ab".to_string(),
+        };
+
+        println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &error, &expected);
+        assert!(error == expected);
+        assert!(error.to_string() == "2:9: invalid numeric literal `12ab`");
+    }
+
+    #[test]
+    fn test_peek() {
+        const INPUT: &str = "let x = 5;";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        assert!(test_lexer.peek().kind == TokenKind::Let);
+        assert!(test_lexer.peek().kind == TokenKind::Let);
+        assert!(test_lexer.peek_n(2).kind == TokenKind::Assignment);
+        assert!(test_lexer.peek_n(1).kind == TokenKind::Identifier("x".into()));
+
+        // Peeking doesn't consume, so tokenizing picks up from the first peeked token.
+        assert!(test_lexer.tokenize().kind == TokenKind::Let);
+        assert!(test_lexer.tokenize().kind == TokenKind::Identifier("x".into()));
+        assert!(test_lexer.peek().kind == TokenKind::Assignment);
+        assert!(test_lexer.peek_n(2).kind == TokenKind::Semicolon);
+        assert!(test_lexer.peek_n(3).kind == TokenKind::EOF);
+
+        let rest: Vec<TokenKind> = test_lexer.map(|token| token.kind).collect();
+        let expected_tokens = vec![
+            TokenKind::Assignment,
+            TokenKind::Number("5".into()),
+            TokenKind::Semicolon,
+            TokenKind::EOF,
+        ];
+        println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &rest, &expected_tokens);
+        assert!(rest == expected_tokens);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        const INPUT: &str = "let ? = 12ab;\nlet y = 1; /* open";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let tokens: Vec<TokenKind> = test_lexer.by_ref().map(|token| token.kind).collect();
+
+        // Lexing carries on past each problem, so every token is still produced.
+        assert!(tokens.len() == 12);
+        assert!(tokens[11] == TokenKind::EOF);
+
+        let expected = vec![
+            LexError {
+                kind: LexErrorKind::IllegalCharacter,
+                span: Span {
+                    start: 4,
+                    end: 5,
+                    line: 1,
+                    column: 5,
+                },
+                snippet: "?".to_string(),
+            },
+            LexError {
+                kind: LexErrorKind::MalformedNumber,
+                span: Span {
+                    start: 8,
+                    end: 12,
+                    line: 1,
+                    column: 9,
+                },
+                snippet: "12ab".to_string(),
+            },
+            LexError {
+                kind: LexErrorKind::UnterminatedComment,
+                span: Span {
+                    start: 25,
+                    end: 32,
+                    line: 2,
+                    column: 12,
+                },
+                snippet: "/* open".to_string(),
+            },
+        ];
+
+        println!(
+            "ACTUAL=`{:?}`, EXPECTED=`{:?}`",
+            test_lexer.diagnostics(),
+            &expected
+        );
+        assert!(test_lexer.diagnostics() == expected);
+        assert!(test_lexer.diagnostics()[0].to_string() == "1:5: illegal character `?`");
+    }
+
+    #[test]
+    fn test_string_literals() {
+        const INPUT: &str = r#"let s = "hello";"" "tab\tquote\"brace\{\}""#;
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Let,
+            TokenKind::Identifier("s".into()),
+            TokenKind::Assignment,
+            TokenKind::String("hello".into()),
+            TokenKind::Semicolon,
+            TokenKind::String("".into()),
+            TokenKind::String("tab\tquote\"brace{}".into()),
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+        assert!(test_lexer.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_interpolated_strings() {
+        const INPUT: &str =
+            r#""hello {name}!" "{a}{b}" "{ {x: 1}["x"] } \{not}" "{f("}")}" "{'}'}""#;
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::InterpolatedString(vec![
+                StringPart::Text("hello ".into()),
+                StringPart::Expression("name".into()),
+                StringPart::Text("!".into()),
+            ]),
+            TokenKind::InterpolatedString(vec![
+                StringPart::Expression("a".into()),
+                StringPart::Expression("b".into()),
+            ]),
+            TokenKind::InterpolatedString(vec![
+                StringPart::Expression(r#" {x: 1}["x"] "#.into()),
+                StringPart::Text(" {not}".into()),
+            ]),
+            TokenKind::InterpolatedString(vec![StringPart::Expression(r#"f("}")"#.into())]),
+            TokenKind::InterpolatedString(vec![StringPart::Expression("'}'".into())]),
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_malformed_strings() {
+        const INPUT: &str = "\"bad \\q\" \"open {x\"";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Error(LexErrorKind::UnknownEscape),
+            TokenKind::Error(LexErrorKind::UnterminatedString),
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+
+        let messages: Vec<String> = test_lexer
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect();
+        println!("{messages:?}");
+        let expected = [
+            "1:6: unknown escape sequence `\\q`",
+            "1:10: unterminated string literal",
+        ];
+        assert!(messages == expected);
+    }
+
+    #[test]
+    fn test_unicode_escapes() {
+        const INPUT: &str =
+            r#""\u{41}\u{1F600}!" '\u{e9}' "\u{D800} {x}" "\u{110000}\u41" '\u{zz}'"#;
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::String("A😀!".into()),
+            TokenKind::Char('é'),
+            TokenKind::Error(LexErrorKind::InvalidUnicodeEscape),
+            TokenKind::Error(LexErrorKind::InvalidUnicodeEscape),
+            TokenKind::Error(LexErrorKind::InvalidUnicodeEscape),
+            TokenKind::EOF,
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+
+        // Each bad escape is reported once, pointing at the escape itself.
+        let messages: Vec<String> = test_lexer
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect();
+        let expected = [
+            "1:30: invalid unicode escape `\\u{D800}`",
+            "1:45: invalid unicode escape `\\u{110000}`",
+            "1:55: invalid unicode escape `\\u`",
+            "1:62: invalid unicode escape `\\u{zz}`",
+        ];
+        println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &messages, &expected);
+        assert!(messages == expected);
+    }
+
+    #[test]
+    fn test_char_literals() {
+        const INPUT: &str = r#"'a' '\n' '\'' '"' 'ï' '{'"#;
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Char('a'),
+            TokenKind::Char('\n'),
+            TokenKind::Char('\''),
+            TokenKind::Char('"'),
+            TokenKind::Char('ï'),
+            TokenKind::Char('{'),
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+        assert!(test_lexer.tokenize().kind == TokenKind::EOF);
+        assert!(test_lexer.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_malformed_char_literals() {
+        const INPUT: &str = "'' 'ab' 'x\n;";
+
+        let mut test_lexer = lexer::Lexer::new(INPUT).unwrap();
+        let expected_tokens = vec![
+            TokenKind::Error(LexErrorKind::EmptyCharacter),
+            TokenKind::Error(LexErrorKind::OverlongCharacter),
+            TokenKind::Error(LexErrorKind::UnterminatedCharacter),
+            TokenKind::Semicolon,
+        ];
+
+        for expected in expected_tokens {
+            let actual = test_lexer.tokenize().kind;
+            println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &actual, &expected);
+            assert!(actual == expected);
+        }
+
+        let messages: Vec<String> = test_lexer
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect();
+        let expected = [
+            "1:1: empty character literal",
+            "1:4: character literal `'ab'` holds more than one character",
+            "1:9: unterminated character literal",
+        ];
+        println!("ACTUAL=`{:?}`, EXPECTED=`{:?}`", &messages, &expected);
+        assert!(messages == expected);
+    }
+
+    /// Not a correctness test: run it with `cargo test --release bench_ -- --ignored --nocapture`
+    /// to see how lexing time grows with the input. Doubling the input should roughly double the
+    /// time taken, rather than quadrupling it.
+    #[test]
+    #[ignore]
+    fn bench_lexing_scales_linearly() {
+        const LINE: &str = "let value = add(x, 10) * 2.5; // naïve comment\n";
+
+        for repeats in [1_000, 2_000, 4_000, 8_000] {
+            let input = LINE.repeat(repeats);
+            let start = Instant::now();
+            let tokens = lexer::Lexer::new(&input).unwrap().count();
+            let elapsed = start.elapsed();
+            println!("{:>7} bytes, {tokens:>6} tokens: {elapsed:?}", input.len());
+        }
+    }
 }
@@ -0,0 +1,15 @@
+//! The lexer and parser for the language, usable on their own so that it can be embedded in
+//! other programs and tested outside of the `plain` binary.
+
+pub mod ast;
+pub mod interner;
+pub mod keyword;
+pub mod lexer;
+pub mod line_index;
+pub mod parser;
+pub mod pipeline;
+pub mod span;
+pub mod token;
+
+#[cfg(test)]
+mod test_support;
@@ -0,0 +1,133 @@
+/// A line/column position in a source file. Both fields start counting at one, and the column
+/// is measured in characters so that non-ASCII text lines up with what an editor shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// An index of the byte offset at which every line of a source file starts.
+/// It is built once per file so that mapping an offset back to a `Position` is a binary search
+/// rather than a rescan of the whole input.
+pub struct LineIndex<'a> {
+    /// The source text the index was built from.
+    source: &'a str,
+
+    /// The byte offset of the first character of each line, in ascending order.
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    /// Creates a new `LineIndex` by scanning the provided source once for newlines.
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(offset, _)| offset + 1))
+            .collect();
+
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// Returns the number of lines in the indexed source.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Converts a byte offset into a `Position`. Offsets past the end of the source are clamped
+    /// to the end of the last line, and offsets inside a character to the start of it.
+    pub fn position(&self, offset: usize) -> Position {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        let column = self.source[start..offset].chars().count() + 1;
+
+        Position {
+            line: line + 1,
+            column,
+        }
+    }
+
+    /// Returns the text of the provided one-based line number, without its line terminator.
+    pub fn line(&self, line: usize) -> Option<&'a str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .map_or(self.source.len(), |next| next - 1);
+
+        Some(self.source[start..end].trim_end_matches('\r'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::line_index::{LineIndex, Position};
+
+    const TEST_INPUT: &str = "let five = 5;\nlet ten = 10;\r\n\nlet naïve = 1;";
+
+    #[test]
+    fn test_line_starts() {
+        let index = LineIndex::new(TEST_INPUT);
+
+        assert!(index.line_count() == 4);
+        assert!(index.line(1) == Some("let five = 5;"));
+        assert!(index.line(2) == Some("let ten = 10;"));
+        assert!(index.line(3) == Some(""));
+        assert!(index.line(4) == Some("let naïve = 1;"));
+        assert!(index.line(0).is_none());
+        assert!(index.line(5).is_none());
+    }
+
+    #[test]
+    fn test_position() {
+        let index = LineIndex::new(TEST_INPUT);
+
+        assert!(index.position(0) == Position { line: 1, column: 1 });
+        assert!(index.position(4) == Position { line: 1, column: 5 });
+        assert!(
+            index.position(13)
+                == Position {
+                    line: 1,
+                    column: 14
+                }
+        );
+        assert!(index.position(14) == Position { line: 2, column: 1 });
+        assert!(index.position(29) == Position { line: 3, column: 1 });
+    }
+
+    #[test]
+    fn test_position_counts_characters() {
+        let index = LineIndex::new(TEST_INPUT);
+        let equals = TEST_INPUT.rfind('=').unwrap();
+
+        // "let naïve " is ten characters but eleven bytes.
+        assert!(
+            index.position(equals)
+                == Position {
+                    line: 4,
+                    column: 11
+                }
+        );
+        assert!(
+            index.position(TEST_INPUT.len() + 10)
+                == Position {
+                    line: 4,
+                    column: 15
+                }
+        );
+    }
+
+    #[test]
+    fn test_position_inside_character() {
+        let index = LineIndex::new("aï");
+
+        // Byte 2 is the middle of "ï", which starts at byte 1.
+        assert!(index.position(2) == Position { line: 1, column: 2 });
+        assert!(index.position(3) == Position { line: 1, column: 3 });
+    }
+}
@@ -1,34 +1,162 @@
-mod lexer;
-mod parser;
-mod token;
-
 use std::env;
 use std::fs;
-use std::io;
+use std::io::{self, Read};
+use std::process;
+use std::time::Instant;
+
+use plain::lexer;
+use plain::line_index::{LineIndex, Position};
+use plain::parser::Parser;
+
+/// The usage line printed when the arguments don't match any of the modes.
+const USAGE: &str = "Usage: [--stats] [check] [script | -e [program] | --eval-stdin]";
 
-fn main() -> () {
+fn main() {
     // main read loop
-    let mut args = env::args();
+    let mut args: Vec<String> = env::args().collect();
+    let show_stats = take_flag(&mut args, "--stats");
+    if take_flag(&mut args, "--eval-stdin") {
+        args.push("-e".to_string());
+    }
 
     match args.len() {
-        1 => eprintln!("Usage: [script]"),
-        2 => {
-            if let Some(file_name) = args.nth(1) {
-                let contents = fs::read_to_string(file_name).unwrap();
-                let lexer = lexer::Lexer::new(contents);
-            };
+        1 => eprintln!("{USAGE}"),
+        2 if args[1] == "-e" => run("<stdin>", &read_stdin(), show_stats),
+        2 if args[1] == "check" => usage(),
+        2 => run(&args[1], &read_file(&args[1]), show_stats),
+        3 if args[1] == "check" => check(&args[2], show_stats),
+        3 if args[1] == "-e" => run("<eval>", &args[2], show_stats),
+        _ => usage(),
+    }
+}
+
+/// Prints the usage line for arguments that don't match any of the modes, and exits with an
+/// error.
+fn usage() -> ! {
+    eprintln!("{USAGE}");
+    process::exit(1);
+}
+
+/// Removes every occurrence of `flag` from `args`, returning whether it was present.
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let before = args.len();
+    args.retain(|arg| arg != flag);
+
+    args.len() != before
+}
+
+/// Reads the script at `file_name` into memory, exiting with an error if it can't be read.
+fn read_file(file_name: &str) -> String {
+    match fs::read_to_string(file_name) {
+        Ok(contents) => contents,
+        Err(error) => {
+            eprintln!("{file_name}: {error}");
+            process::exit(1);
         }
-        3.. => {}
-        _ => (),
     }
+}
 
-    if args.len() <= 1 {
-        ()
+/// Reads a whole program from stdin, which is how `-e` without an argument and `--eval-stdin`
+/// accept heredocs. The text is kept verbatim so line numbers match the heredoc.
+fn read_stdin() -> String {
+    let mut source = String::new();
+    if let Err(error) = io::stdin().read_to_string(&mut source) {
+        eprintln!("<stdin>: {error}");
+        process::exit(1);
     }
 
-    if args.len() == 2 {}
+    source
+}
 
-    let contents = todo!(); // fs::read_to_string();
+/// Lexes the program `source`, named `name` for reporting, and prints its tokens. With
+/// `show_stats`, a summary of the run is printed to stderr afterwards so it doesn't mix with the
+/// program's output.
+fn run(name: &str, source: &str, show_stats: bool) {
+    let start = Instant::now();
 
-    ()
+    let mut tokens_lexed = 0;
+    if let Some(mut lexer) = lexer::Lexer::new(source) {
+        match lexer.tokenize_all() {
+            Ok(tokens) => {
+                tokens.iter().for_each(|token| println!("{token:?}"));
+                tokens_lexed = tokens.len();
+            }
+            Err(error) => {
+                eprintln!("{name}:{error}");
+                print_source_line(&LineIndex::new(source), error.span.start);
+                process::exit(1);
+            }
+        }
+    }
+
+    if show_stats {
+        print_stats(name, start, tokens_lexed);
+    }
+}
+
+/// Prints the line of the source holding the byte `offset`, with a caret under the character at
+/// that offset, so a diagnostic shows the code it is about.
+fn print_source_line(index: &LineIndex, offset: usize) {
+    let Position { line, column } = index.position(offset);
+    let Some(text) = index.line(line) else {
+        return;
+    };
+
+    // Tabs are kept in the padding so the caret lines up however wide they are shown.
+    let padding: String = text
+        .chars()
+        .take(column - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    eprintln!("    {text}");
+    eprintln!("    {padding}^");
+}
+
+/// Prints the `--stats` summary of a run over `name` that began at `start`, to stderr so it
+/// doesn't mix with the program's output.
+fn print_stats(name: &str, start: Instant, tokens_lexed: usize) {
+    eprintln!("stats for {name}:");
+    eprintln!("wall time:    {:?}", start.elapsed());
+    eprintln!("tokens lexed: {tokens_lexed}");
+}
+
+/// Lexes and parses the script at `file_name` without running it, reporting every problem found.
+/// Problems with the tokens themselves are reported first, and the script is only parsed once
+/// there are none, since a bad token would mostly just cause parse errors of its own. With
+/// `show_stats`, the same summary as a run is printed afterwards. Exits with a nonzero status
+/// when there was anything to report, so editors and CI can use it as a quick pass/fail gate.
+fn check(file_name: &str, show_stats: bool) {
+    let start = Instant::now();
+    let contents = read_file(file_name);
+
+    let index = LineIndex::new(&contents);
+
+    let mut tokens = Vec::new();
+    let mut failed = false;
+    if let Some(mut lexer) = lexer::Lexer::new(&contents) {
+        tokens = lexer.by_ref().collect();
+        for diagnostic in lexer.diagnostics() {
+            eprintln!("{file_name}:{diagnostic}");
+            print_source_line(&index, diagnostic.span.start);
+        }
+        failed = !lexer.diagnostics().is_empty();
+    }
+    let tokens_lexed = tokens.len();
+
+    if !failed {
+        let mut parser = Parser::new(tokens);
+        parser.parse_program();
+        for error in parser.errors() {
+            eprintln!("{file_name}:{error}");
+            print_source_line(&index, error.span.start);
+        }
+        failed = !parser.errors().is_empty();
+    }
+
+    if show_stats {
+        print_stats(file_name, start, tokens_lexed);
+    }
+    if failed {
+        process::exit(1);
+    }
 }
@@ -0,0 +1,405 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+
+use crate::ast::{
+    Expression, Identifier, InfixOperator, LetStatement, Literal, PrefixOperator, Program,
+    Statement,
+};
+use crate::interner::Interner;
+use crate::lexer::{LexError, LexErrorKind};
+use crate::pipeline::TokenStream;
+use crate::span::Span;
+use crate::token::{Precedence, Token, TokenKind};
+
+/// An error produced when the tokens don't form a valid program, such as a `let` with no name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// A description of the problem, quoting the offending token.
+    pub message: String,
+
+    /// The location of the offending token.
+    pub span: Span,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Span { line, column, .. } = self.span;
+        write!(f, "{line}:{column}: {}", self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+/// A Pratt parser turning a stream of tokens into a `Program`.
+pub struct Parser<'a> {
+    /// The tokens being parsed.
+    tokens: TokenStream<'a>,
+
+    /// Every problem found in the tokens so far.
+    errors: Vec<ParseError>,
+
+    /// The symbol table every identifier is interned in.
+    interner: Interner<'a>,
+}
+
+impl<'a> Parser<'a> {
+    /// Creates a new `Parser` reading from `tokens`, which may be a `Lexer`, a `Vec` of tokens or
+    /// any other `TokenStream`.
+    pub fn new(tokens: impl Into<TokenStream<'a>>) -> Self {
+        Self {
+            tokens: tokens.into(),
+            errors: Vec::new(),
+            interner: Interner::new(),
+        }
+    }
+
+    /// Returns every problem found in the tokens that have been parsed so far. Parsing carries on
+    /// past each one by skipping to the next statement, so once the whole program has been parsed
+    /// this holds all of them.
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// Returns the symbol table holding every identifier parsed so far, for resolving the
+    /// `Symbol` of an `Identifier` back to its name.
+    pub fn interner(&self) -> &Interner<'a> {
+        &self.interner
+    }
+
+    /// Parses every remaining statement. Statements with an error are left out of the `Program`
+    /// and recorded in `errors()` instead.
+    pub fn parse_program(&mut self) -> Program<'a> {
+        let mut program = Program::default();
+        while self.peek().kind != TokenKind::EOF {
+            match self.parse_statement() {
+                Ok(statement) => program.statements.push(statement),
+                Err(error) => {
+                    self.errors.push(error);
+                    self.synchronize();
+                }
+            }
+        }
+
+        program
+    }
+
+    /// Returns the next token without consuming it. Doc comments aren't attached to anything yet,
+    /// so they are skipped over wherever they appear.
+    fn peek(&mut self) -> &Token<'a> {
+        while let TokenKind::DocComment(_) = self.tokens.peek().kind {
+            self.tokens.next_token();
+        }
+
+        self.tokens.peek()
+    }
+
+    /// Consumes and returns the next token, skipping any doc comments before it.
+    fn next_token(&mut self) -> Token<'a> {
+        self.peek();
+        self.tokens.next_token()
+    }
+
+    /// Skips the rest of a statement that failed to parse, up to and including its `;` or up to
+    /// the `let` starting the next statement, so parsing can carry on with the next one.
+    fn synchronize(&mut self) {
+        loop {
+            match self.peek().kind {
+                TokenKind::EOF | TokenKind::Let => return,
+                TokenKind::Semicolon => {
+                    self.next_token();
+                    return;
+                }
+                _ => {
+                    self.next_token();
+                }
+            }
+        }
+    }
+
+    /// Parses a single statement, starting at the next token.
+    fn parse_statement(&mut self) -> Result<Statement<'a>, ParseError> {
+        match self.peek().kind {
+            TokenKind::Let => self.parse_let_statement().map(Statement::Let),
+            _ => {
+                let expression = self.parse_expression(Precedence::Lowest)?;
+                self.expect(TokenKind::Semicolon, "`;` after the expression")?;
+                Ok(Statement::Expression(expression))
+            }
+        }
+    }
+
+    /// Parses a `let name = value;` statement.
+    fn parse_let_statement(&mut self) -> Result<LetStatement<'a>, ParseError> {
+        self.expect(TokenKind::Let, "`let`")?;
+
+        let name = match &self.peek().kind {
+            TokenKind::Identifier(name) => name.clone(),
+            _ => return Err(unexpected(self.peek(), "a name after `let`")),
+        };
+        let name = self.identifier(name);
+        self.next_token();
+
+        self.expect(TokenKind::Assignment, &format!("`=` after `let {name}`"))?;
+        let value = self.parse_expression(Precedence::Lowest)?;
+        self.expect(TokenKind::Semicolon, "`;` after the value of the `let`")?;
+
+        Ok(LetStatement { name, value })
+    }
+
+    /// Parses an expression whose operators all bind more tightly than `precedence`. This is the
+    /// heart of the Pratt parser: an operand is parsed first, then infix operators are folded
+    /// into it for as long as they bind tightly enough.
+    fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression<'a>, ParseError> {
+        let mut left = self.parse_prefix()?;
+
+        while let Some(next) = self
+            .peek()
+            .kind
+            .precedence()
+            .filter(|&next| precedence < next)
+        {
+            let Some(operator) = InfixOperator::from_token(&self.peek().kind) else {
+                break;
+            };
+            self.next_token();
+
+            let right = self.parse_expression(next)?;
+            left = Expression::Infix {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(left)
+    }
+
+    /// Parses the operand at the start of an expression: an identifier, a literal, a prefix
+    /// operator applied to its operand, or a parenthesised expression. A token that can't start
+    /// an expression is left unconsumed.
+    fn parse_prefix(&mut self) -> Result<Expression<'a>, ParseError> {
+        let token = self.peek().clone();
+        let literal = match token.kind {
+            TokenKind::Identifier(name) => {
+                self.next_token();
+                return Ok(Expression::Identifier(self.identifier(name)));
+            }
+            TokenKind::Number(text) => Literal::Number(text),
+            TokenKind::Float(text) => Literal::Float(text),
+            TokenKind::RadixNumber(radix, digits) => Literal::RadixNumber(radix, digits),
+            TokenKind::String(text) => Literal::String(text),
+            TokenKind::InterpolatedString(parts) => Literal::InterpolatedString(parts),
+            TokenKind::Char(character) => Literal::Char(character),
+            TokenKind::True => Literal::Boolean(true),
+            TokenKind::False => Literal::Boolean(false),
+            TokenKind::Null => Literal::Null,
+            TokenKind::LeftParen => {
+                self.next_token();
+                let expression = self.parse_expression(Precedence::Lowest)?;
+                self.expect(TokenKind::RightParen, "`)` to close the `(`")?;
+                return Ok(expression);
+            }
+            ref kind => match PrefixOperator::from_token(kind) {
+                Some(operator) => {
+                    self.next_token();
+                    let right = self.parse_expression(Precedence::Prefix)?;
+                    return Ok(Expression::Prefix {
+                        operator,
+                        right: Box::new(right),
+                    });
+                }
+                None => return Err(unexpected(&token, "an expression")),
+            },
+        };
+        self.next_token();
+
+        Ok(Expression::Literal(literal))
+    }
+
+    /// Builds the `Identifier` for `name`, interning it so that every use of the same name shares
+    /// one `Symbol`.
+    fn identifier(&mut self, name: Cow<'a, str>) -> Identifier<'a> {
+        let symbol = self.interner.intern(name.clone());
+        Identifier { name, symbol }
+    }
+
+    /// Consumes the next token if it is of the `expected` kind, or otherwise leaves it and returns
+    /// an error saying what was `wanted` instead.
+    fn expect(&mut self, expected: TokenKind, wanted: &str) -> Result<Token<'a>, ParseError> {
+        if self.peek().kind != expected {
+            return Err(unexpected(self.peek(), wanted));
+        }
+
+        Ok(self.next_token())
+    }
+}
+
+/// Builds the error for finding `token` where `wanted` should have been. A token the lexer
+/// couldn't make sense of is reported as the lexer's own problem with it instead, since that is
+/// what needs fixing.
+fn unexpected(token: &Token, wanted: &str) -> ParseError {
+    let message = match token.kind {
+        TokenKind::EOF => format!("expected {wanted} but found the end of the input"),
+        // The lexeme of a literal with a bad escape is the whole literal, not just the escape.
+        TokenKind::Error(
+            kind @ (LexErrorKind::UnknownEscape | LexErrorKind::InvalidUnicodeEscape),
+        ) => {
+            format!("{kind} in `{token}`")
+        }
+        TokenKind::Error(kind) => LexError {
+            kind,
+            span: token.span,
+            snippet: token.lexeme.to_string(),
+        }
+        .message(),
+        _ => format!("expected {wanted} but found `{token}`"),
+    };
+
+    ParseError {
+        message,
+        span: token.span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{Expression, LetStatement, Literal, Statement};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use crate::test_support::{identifiers, token};
+    use crate::token::TokenKind;
+
+    fn parse(input: &str) -> Parser<'_> {
+        Parser::new(Lexer::new(input).unwrap())
+    }
+
+    #[test]
+    fn test_let_statements() {
+        const INPUT: &str = "let x = 5;\nlet name = \"plain\";\nlet y = x;";
+
+        let mut parser = parse(INPUT);
+        let program = parser.parse_program();
+        let mut identifier = identifiers();
+        let expected = vec![
+            Statement::Let(LetStatement {
+                name: identifier("x"),
+                value: Expression::Literal(Literal::Number("5".into())),
+            }),
+            Statement::Let(LetStatement {
+                name: identifier("name"),
+                value: Expression::Literal(Literal::String("plain".into())),
+            }),
+            Statement::Let(LetStatement {
+                name: identifier("y"),
+                value: Expression::Identifier(identifier("x")),
+            }),
+        ];
+
+        println!(
+            "ACTUAL=`{:?}`, EXPECTED=`{:?}`",
+            &program.statements, &expected
+        );
+        assert!(parser.errors().is_empty());
+        assert!(program.statements == expected);
+        assert!(parser.interner().len() == 3);
+        let Statement::Let(LetStatement { name, .. }) = &program.statements[2] else {
+            panic!("expected a let statement");
+        };
+        assert!(parser.interner().resolve(name.symbol) == "y");
+    }
+
+    #[test]
+    fn test_let_expression_precedence() {
+        const INPUT: &str = "let a = 1 + 2 * -3;\nlet b = (1 + 2) * 3 == 9 && !done;";
+
+        let mut parser = parse(INPUT);
+        let program = parser.parse_program();
+
+        println!("{program}");
+        assert!(parser.errors().is_empty());
+        assert!(
+            program.to_string()
+                == "let a = (1 + (2 * (-3)));\nlet b = ((((1 + 2) * 3) == 9) && (!done));\n"
+        );
+    }
+
+    #[test]
+    fn test_let_errors() {
+        const INPUT: &str = "let = 5;\nlet x 5;\nlet y = ;\nlet z = 1\nlet ok = 2;";
+
+        let mut parser = parse(INPUT);
+        let program = parser.parse_program();
+        let messages: Vec<String> = parser.errors().iter().map(|e| e.to_string()).collect();
+        let expected = vec![
+            "1:5: expected a name after `let` but found `=`",
+            "2:7: expected `=` after `let x` but found `5`",
+            "3:9: expected an expression but found `;`",
+            "5:1: expected `;` after the value of the `let` but found `let`",
+        ];
+
+        // Each error skips to the end of its statement, so `let ok` is still parsed.
+        println!("ACTUAL=`{messages:?}`, EXPECTED=`{expected:?}`");
+        assert!(messages == expected);
+        assert!(program.to_string() == "let ok = 2;\n");
+    }
+
+    #[test]
+    fn test_let_missing_semicolon_at_end() {
+        let mut parser = parse("let x = 5");
+        let program = parser.parse_program();
+
+        assert!(program.statements.is_empty());
+        assert!(
+            parser.errors()[0].to_string()
+                == "1:10: expected `;` after the value of the `let` but found the end of the input"
+        );
+    }
+
+    #[test]
+    fn test_doc_comments_are_skipped() {
+        const INPUT: &str =
+            "/// The answer.\nlet x = 42;\n/// Twice the answer.\nlet y = x\n/// by\n* 2;";
+
+        let mut parser = parse(INPUT);
+        let program = parser.parse_program();
+
+        assert!(parser.errors().is_empty());
+        assert!(program.to_string() == "let x = 42;\nlet y = (x * 2);\n");
+    }
+
+    #[test]
+    fn test_lex_errors() {
+        const INPUT: &str = "let x = 1 + 2x;\nlet y ? 3;\nlet s = \"bad \\q\";\nlet s = \"open";
+
+        let mut parser = parse(INPUT);
+        parser.parse_program();
+        let messages: Vec<String> = parser.errors().iter().map(|e| e.to_string()).collect();
+        let expected = vec![
+            "1:13: invalid numeric literal `2x`",
+            "2:7: illegal character `?`",
+            "3:9: unknown escape sequence in `\"bad \\q\"`",
+            "4:9: unterminated string literal",
+        ];
+
+        println!("ACTUAL=`{messages:?}`, EXPECTED=`{expected:?}`");
+        assert!(messages == expected);
+    }
+
+    #[test]
+    fn test_parse_hand_written_tokens() {
+        let tokens = vec![
+            token(TokenKind::Let, "let", 0),
+            token(TokenKind::Identifier("t".into()), "t", 4),
+            token(TokenKind::Assignment, "=", 6),
+            token(TokenKind::True, "true", 8),
+            token(TokenKind::Semicolon, ";", 12),
+        ];
+
+        let mut parser = Parser::new(tokens);
+        let program = parser.parse_program();
+
+        assert!(parser.errors().is_empty());
+        assert!(program.to_string() == "let t = true;\n");
+    }
+}
@@ -0,0 +1,288 @@
+use std::borrow::Cow;
+
+use crate::lexer::Lexer;
+use crate::span::Span;
+use crate::token::{Token, TokenKind};
+
+/// A stage of the pipeline that produces tokens, such as the `Lexer` itself or a filter wrapped
+/// around one. Later stages only ask for the next token, so custom stages can be inserted between
+/// the lexer and the parser without either of them knowing.
+pub trait TokenSource<'a> {
+    /// Returns the next token of the stream, or `None` once it is exhausted.
+    fn next_token(&mut self) -> Option<Token<'a>>;
+}
+
+impl<'a> TokenSource<'a> for Lexer<'a> {
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        self.next()
+    }
+}
+
+impl<'a> TokenSource<'a> for std::vec::IntoIter<Token<'a>> {
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        self.next()
+    }
+}
+
+/// A saved position in a `TokenStream`, returned by `TokenStream::checkpoint()` so that the
+/// stream can later be rewound to it.
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoint(usize);
+
+/// The stream of tokens the parser consumes, at the end of the pipeline. It can be built from a
+/// `Lexer`, any other `TokenSource`, or a `Vec` of hand-written tokens, so the parser can be
+/// tested without going through the lexer. Like the `Lexer`, it ends with a `TokenKind::EOF`
+/// that repeats on every further call, adding one if its source doesn't end with one.
+pub struct TokenStream<'a> {
+    /// The stage the tokens are pulled from.
+    source: Box<dyn TokenSource<'a> + 'a>,
+
+    /// Every token pulled from the source so far. They are kept after being returned, so that
+    /// rewinding to a checkpoint is just moving `position` back.
+    tokens: Vec<Token<'a>>,
+
+    /// The index in `tokens` of the token the next call to `next_token()` returns.
+    position: usize,
+
+    /// The `TokenKind::EOF` ending the stream, once the source has been exhausted.
+    eof: Option<Token<'a>>,
+
+    /// The location just past the last token pulled from the source, where a missing
+    /// `TokenKind::EOF` is placed.
+    end: Span,
+}
+
+impl<'a> TokenStream<'a> {
+    /// Creates a new `TokenStream` pulling its tokens from `source`.
+    pub fn new(source: impl TokenSource<'a> + 'a) -> Self {
+        Self {
+            source: Box::new(source),
+            tokens: Vec::new(),
+            position: 0,
+            eof: None,
+            end: Span {
+                start: 0,
+                end: 0,
+                line: 1,
+                column: 1,
+            },
+        }
+    }
+
+    /// Returns the next token of the stream, or `TokenKind::EOF` once it is exhausted.
+    pub fn next_token(&mut self) -> Token<'a> {
+        let token = self.peek().clone();
+        if self.position < self.tokens.len() {
+            self.position += 1;
+        }
+
+        token
+    }
+
+    /// Returns the token the next call to `next_token()` will return, without consuming it.
+    pub fn peek(&mut self) -> &Token<'a> {
+        self.peek_n(0)
+    }
+
+    /// Returns the token `k` tokens ahead without consuming anything, where `peek_n(0)` is the
+    /// same as `peek()`. Looking past the end of the stream gives `TokenKind::EOF`.
+    pub fn peek_n(&mut self, k: usize) -> &Token<'a> {
+        while self.eof.is_none() && self.tokens.len() <= self.position + k {
+            self.pull();
+        }
+
+        match self.tokens.get(self.position + k) {
+            Some(token) => token,
+            None => self.eof.as_ref().expect("the source has been exhausted"),
+        }
+    }
+
+    /// Returns whether every token has been consumed, so that `next_token()` will return
+    /// `TokenKind::EOF`.
+    pub fn is_at_end(&mut self) -> bool {
+        self.peek().kind == TokenKind::EOF
+    }
+
+    /// Saves the current position in the stream, so that the parser can speculatively read ahead
+    /// and then `rewind()` to it if that doesn't work out.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.position)
+    }
+
+    /// Restores the position saved by `checkpoint()`, so the tokens read since then are returned
+    /// again.
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.position = checkpoint.0;
+    }
+
+    /// Pulls the next token from the source into `tokens`, or records the `TokenKind::EOF`
+    /// ending the stream once the source is exhausted.
+    fn pull(&mut self) {
+        let token = self.source.next_token().unwrap_or(Token {
+            kind: TokenKind::EOF,
+            lexeme: Cow::Borrowed(""),
+            span: self.end,
+        });
+
+        if token.kind == TokenKind::EOF {
+            self.eof = Some(token);
+        } else {
+            self.end = span_after(&token);
+            self.tokens.push(token);
+        }
+    }
+}
+
+/// Returns the empty span just past `token`, following any line breaks in its lexeme, as a block
+/// comment or a string literal may have.
+fn span_after(token: &Token) -> Span {
+    let (line, column) = match token.lexeme.rsplit_once('\n') {
+        Some((before, last)) => (
+            token.span.line + before.matches('\n').count() + 1,
+            last.chars().count() + 1,
+        ),
+        None => (
+            token.span.line,
+            token.span.column + token.lexeme.chars().count(),
+        ),
+    };
+
+    Span {
+        start: token.span.end,
+        end: token.span.end,
+        line,
+        column,
+    }
+}
+
+impl<'a> From<Lexer<'a>> for TokenStream<'a> {
+    fn from(lexer: Lexer<'a>) -> Self {
+        Self::new(lexer)
+    }
+}
+
+impl<'a> From<Vec<Token<'a>>> for TokenStream<'a> {
+    fn from(tokens: Vec<Token<'a>>) -> Self {
+        Self::new(tokens.into_iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::Lexer;
+    use crate::pipeline::{TokenSource, TokenStream};
+    use crate::span::Span;
+    use crate::test_support::token;
+    use crate::token::{Token, TokenKind};
+
+    /// A custom stage that drops every semicolon from the stream it wraps.
+    struct WithoutSemicolons<S> {
+        source: S,
+    }
+
+    impl<'a, S: TokenSource<'a>> TokenSource<'a> for WithoutSemicolons<S> {
+        fn next_token(&mut self) -> Option<Token<'a>> {
+            loop {
+                match self.source.next_token()? {
+                    Token {
+                        kind: TokenKind::Semicolon,
+                        ..
+                    } => continue,
+                    token => return Some(token),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_lexer_source() {
+        let mut source = Lexer::new("let x;").unwrap();
+
+        assert!(source.next_token().map(|t| t.kind) == Some(TokenKind::Let));
+        assert!(source.next_token().map(|t| t.kind) == Some(TokenKind::Identifier("x".into())));
+        assert!(source.next_token().map(|t| t.kind) == Some(TokenKind::Semicolon));
+        assert!(source.next_token().map(|t| t.kind) == Some(TokenKind::EOF));
+        assert!(source.next_token().is_none());
+    }
+
+    #[test]
+    fn test_custom_stage() {
+        let mut source = WithoutSemicolons {
+            source: Lexer::new("x; y;").unwrap(),
+        };
+
+        assert!(source.next_token().map(|t| t.kind) == Some(TokenKind::Identifier("x".into())));
+        assert!(source.next_token().map(|t| t.kind) == Some(TokenKind::Identifier("y".into())));
+        assert!(source.next_token().map(|t| t.kind) == Some(TokenKind::EOF));
+        assert!(source.next_token().is_none());
+    }
+
+    #[test]
+    fn test_stream_from_lexer() {
+        let mut stream = TokenStream::from(Lexer::new("let x").unwrap());
+
+        assert!(stream.peek_n(1).kind == TokenKind::Identifier("x".into()));
+        assert!(stream.next_token().kind == TokenKind::Let);
+        assert!(stream.next_token().kind == TokenKind::Identifier("x".into()));
+        assert!(stream.is_at_end());
+        assert!(stream.next_token().kind == TokenKind::EOF);
+        assert!(stream.next_token().kind == TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_stream_from_tokens() {
+        let mut stream = TokenStream::from(vec![
+            token(TokenKind::Number("1".into()), "1", 0),
+            token(TokenKind::Plus, "+", 2),
+            token(TokenKind::Number("2".into()), "2", 4),
+        ]);
+
+        assert!(stream.next_token().kind == TokenKind::Number("1".into()));
+        assert!(stream.next_token().kind == TokenKind::Plus);
+        assert!(!stream.is_at_end());
+        assert!(stream.next_token().kind == TokenKind::Number("2".into()));
+
+        // The hand-written tokens have no EOF, so one is added just past the last of them.
+        let eof = stream.next_token();
+        assert!(eof.kind == TokenKind::EOF);
+        assert!(eof.span.start == 5 && eof.span.column == 6 && eof.span.is_empty());
+        assert!(stream.next_token() == eof);
+    }
+
+    #[test]
+    fn test_eof_after_multi_line_token() {
+        let comment = Token {
+            kind: TokenKind::BlockComment(" one\ntwo ".into()),
+            lexeme: "/* one\ntwo */".into(),
+            span: Span {
+                start: 2,
+                end: 15,
+                line: 1,
+                column: 3,
+            },
+        };
+        let mut stream = TokenStream::from(vec![comment]);
+
+        // The missing EOF goes just past the `*/` on the second line.
+        stream.next_token();
+        let eof = stream.next_token();
+        assert!(eof.kind == TokenKind::EOF);
+        assert!(eof.span.start == 15 && eof.span.line == 2 && eof.span.column == 7);
+    }
+
+    #[test]
+    fn test_checkpoint_rewind() {
+        let mut stream = TokenStream::from(Lexer::new("f(a) + g").unwrap());
+        assert!(stream.next_token().kind == TokenKind::Identifier("f".into()));
+
+        let checkpoint = stream.checkpoint();
+        let first: Vec<_> = (0..5).map(|_| stream.next_token()).collect();
+        assert!(stream.is_at_end());
+
+        stream.rewind(checkpoint);
+        assert!(stream.peek().kind == TokenKind::LeftParen);
+        let second: Vec<_> = (0..5).map(|_| stream.next_token()).collect();
+        assert!(first == second);
+        assert!(stream.next_token().kind == TokenKind::EOF);
+    }
+}
@@ -0,0 +1,23 @@
+/// The region of source a token or diagnostic covers. `start` and `end` are byte offsets into the
+/// source, so the lexeme is `&source[start..end]`, while `line` and `column` give the one-based
+/// position of `start` for printing, with the column counted in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// Returns the length of the span in bytes.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns whether the span covers no source at all.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
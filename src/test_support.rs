@@ -0,0 +1,31 @@
+//! Fixtures shared by the unit tests of several modules.
+
+use crate::ast::Identifier;
+use crate::interner::Interner;
+use crate::span::Span;
+use crate::token::{Token, TokenKind};
+
+/// Builds a token by hand, as if it had been lexed from `lexeme` starting `start` bytes into the
+/// first line of the input.
+pub fn token(kind: TokenKind<'static>, lexeme: &'static str, start: usize) -> Token<'static> {
+    Token {
+        kind,
+        lexeme: lexeme.into(),
+        span: Span {
+            start,
+            end: start + lexeme.len(),
+            line: 1,
+            column: start + 1,
+        },
+    }
+}
+
+/// Returns a function building identifiers interned in a fresh `Interner`. Calling it for names
+/// in the order they appear in the source gives them the same symbols the parser would.
+pub fn identifiers() -> impl FnMut(&'static str) -> Identifier<'static> {
+    let mut names = Interner::new();
+    move |name| Identifier {
+        name: name.into(),
+        symbol: names.intern(name),
+    }
+}
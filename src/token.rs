@@ -1,27 +1,74 @@
-/// Parser API
-
-// #[derive(Debug)]
-// pub struct Token {
-//     pub token_type: TokenType,
-//     pub literal: String,
-// }
-//
-// impl Token {
-//     pub fn new(token_type: TokenType, literal: String) -> Self {
-//         Self {
-//             token_type,
-//             literal,
-//         }
-//     }
-// }
-
-#[derive(Debug, PartialEq)]
-pub enum Token {
-    // a-z, A-Z
-    Character(char),
+//! The tokens the lexer breaks plain source text into.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::lexer::LexErrorKind;
+use crate::span::Span;
+
+/// A single token of the input: what kind of token it is, the exact source text it was lexed
+/// from, and where that text is.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Token<'a> {
+    /// The kind of the token, along with its value for literals and identifiers.
+    pub kind: TokenKind<'a>,
+
+    /// The source text of the token exactly as written, including any quotes, prefixes, escape
+    /// sequences or digit separators that `kind` has already resolved.
+    pub lexeme: Cow<'a, str>,
+
+    /// The location of `lexeme` in the input.
+    pub span: Span,
+}
+
+/// A piece of an interpolated string literal such as `"hello {name}"`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StringPart<'a> {
+    /// Literal text, with any escape sequences already resolved.
+    Text(Cow<'a, str>),
+
+    /// The source of an expression embedded between `{` and `}`, left for the parser to parse.
+    Expression(Cow<'a, str>),
+}
+
+/// The base of an integer literal written with a `0x`, `0o` or `0b` prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Radix {
+    Binary,
+    Octal,
+    Hexadecimal,
+}
+
+impl Radix {
+    /// Returns the numeric base of the radix, as expected by `char::is_digit` and
+    /// `from_str_radix`.
+    pub fn base(self) -> u32 {
+        match self {
+            Radix::Binary => 2,
+            Radix::Octal => 8,
+            Radix::Hexadecimal => 16,
+        }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TokenKind<'a> {
+    // 'a'
+    Char(char),
+
+    // "..."
+    String(Cow<'a, str>),
+    InterpolatedString(Vec<StringPart<'a>>),
 
     // 0-9
-    Number(String),
+    Number(Cow<'a, str>),
+    Float(Cow<'a, str>),
+    RadixNumber(Radix, Cow<'a, str>),
 
     // ()
     LeftParen,
@@ -39,26 +86,48 @@ pub enum Token {
     Semicolon,
     Comma,
 
+    // . ..
+    Dot,
+    DotDot,
+
+    // : ::
+    Colon,
+    DoubleColon,
+
+    // -> =>
+    Arrow,
+    FatArrow,
+
     // Unused Symbols
     Ampersand,
     Asperand,
     Carrot,
     Dollar,
+    Pipe,
     Pound,
     Tilde,
 
     // Operators
+    And,
     Assignment,
     Asterisk,
+    AsteriskAssignment,
     Bang,
     Equals,
     GreaterThan,
+    GreaterThanOrEqual,
     LessThan,
+    LessThanOrEqual,
     Minus,
+    MinusAssignment,
     NotEquals,
+    Or,
     Percent,
+    PercentAssignment,
     Plus,
+    PlusAssignment,
     Slash,
+    SlashAssignment,
 
     // Keywords
     Define,
@@ -68,32 +137,474 @@ pub enum Token {
     False,
     If,
     Else,
-    ElseIf,
     Return,
+    While,
+    For,
+    In,
+    Break,
+    Continue,
+    Null,
+    Const,
+
+    // A keyword added by an embedder through a `KeywordTable`
+    Keyword(Cow<'a, str>),
 
     // Special lexer types
-    Unknown(String),
-    Identifier(String),
-    Illegal(String),
-    Whitespace(String),
+    Identifier(Cow<'a, str>),
+    Error(LexErrorKind),
+    Whitespace(Cow<'a, str>),
+    Comment(Cow<'a, str>),
+    BlockComment(Cow<'a, str>),
+    DocComment(Cow<'a, str>),
     EOF,
 }
 
-impl Token {
-    pub fn keyword(keyword_str: &str) -> Token {
+impl<'a> TokenKind<'a> {
+    /// Returns the default keyword `keyword_str` lexes as, or an identifier if it isn't one. The
+    /// `Lexer` goes through a `KeywordTable`, which falls back to this for any word it doesn't
+    /// override.
+    pub fn keyword(keyword_str: &'a str) -> TokenKind<'a> {
         match keyword_str {
-            "func" => Token::Function,
-            "let" => Token::Let,
-            "true" => Token::True,
-            "false" => Token::False,
-            "if" => Token::If,
-            "else" => Token::Else,
-            "else if" => Token::ElseIf,
-            "return" => Token::Return,
-            _ => Token::Identifier(keyword_str.to_string()),
+            "func" => TokenKind::Function,
+            "let" => TokenKind::Let,
+            "true" => TokenKind::True,
+            "false" => TokenKind::False,
+            "if" => TokenKind::If,
+            "else" => TokenKind::Else,
+            "return" => TokenKind::Return,
+            "while" => TokenKind::While,
+            "for" => TokenKind::For,
+            "in" => TokenKind::In,
+            "break" => TokenKind::Break,
+            "continue" => TokenKind::Continue,
+            "null" => TokenKind::Null,
+            "const" => TokenKind::Const,
+            _ => TokenKind::Identifier(Cow::Borrowed(keyword_str)),
+        }
+    }
+
+    /// Returns whether the token is an operator, including the assignment operators.
+    pub fn is_operator(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::And
+                | TokenKind::Assignment
+                | TokenKind::Asterisk
+                | TokenKind::AsteriskAssignment
+                | TokenKind::Bang
+                | TokenKind::Equals
+                | TokenKind::GreaterThan
+                | TokenKind::GreaterThanOrEqual
+                | TokenKind::LessThan
+                | TokenKind::LessThanOrEqual
+                | TokenKind::Minus
+                | TokenKind::MinusAssignment
+                | TokenKind::NotEquals
+                | TokenKind::Or
+                | TokenKind::Percent
+                | TokenKind::PercentAssignment
+                | TokenKind::Plus
+                | TokenKind::PlusAssignment
+                | TokenKind::Slash
+                | TokenKind::SlashAssignment
+        )
+    }
+
+    /// Returns whether the token is a keyword, either a built-in one or one added through a
+    /// `KeywordTable`.
+    pub fn is_keyword(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::Define
+                | TokenKind::Function
+                | TokenKind::Let
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::If
+                | TokenKind::Else
+                | TokenKind::Return
+                | TokenKind::While
+                | TokenKind::For
+                | TokenKind::In
+                | TokenKind::Break
+                | TokenKind::Continue
+                | TokenKind::Null
+                | TokenKind::Const
+                | TokenKind::Keyword(_)
+        )
+    }
+
+    /// Returns whether the token is a literal value. `true`, `false` and `null` count as literals
+    /// as well as keywords.
+    pub fn is_literal(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::Char(_)
+                | TokenKind::String(_)
+                | TokenKind::InterpolatedString(_)
+                | TokenKind::Number(_)
+                | TokenKind::Float(_)
+                | TokenKind::RadixNumber(..)
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::Null
+        )
+    }
+
+    /// Returns how tightly the token binds when it follows an expression, or `None` if it can't
+    /// continue one. `(` and `[` continue an expression as a call and an index.
+    pub fn precedence(&self) -> Option<Precedence> {
+        let precedence = match self {
+            TokenKind::Or => Precedence::LogicalOr,
+            TokenKind::And => Precedence::LogicalAnd,
+            TokenKind::Equals | TokenKind::NotEquals => Precedence::Equals,
+            TokenKind::LessThan
+            | TokenKind::LessThanOrEqual
+            | TokenKind::GreaterThan
+            | TokenKind::GreaterThanOrEqual => Precedence::LessGreater,
+            TokenKind::Plus | TokenKind::Minus => Precedence::Sum,
+            TokenKind::Asterisk | TokenKind::Slash | TokenKind::Percent => Precedence::Product,
+            TokenKind::LeftParen => Precedence::Call,
+            TokenKind::LeftBracket => Precedence::Index,
+            _ => return None,
+        };
+
+        Some(precedence)
+    }
+}
+
+/// How tightly an operator binds its operands, from loosest to tightest, for the Pratt parser.
+/// `&&` and `||` sit below the comparisons so that `a == b && c < d` groups the comparisons first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precedence {
+    Lowest,
+    LogicalOr,
+    LogicalAnd,
+    Equals,      // ==
+    LessGreater, // > or <
+    Sum,         // +
+    Product,     // *
+    Prefix,      // -x or !x
+    Call,        // func(x)
+    Index,       // array[index]
+}
+
+impl fmt::Display for Token<'_> {
+    /// Prints the token exactly as it was written in the source.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            TokenKind::EOF => write!(f, "{}", self.kind),
+            _ => write!(f, "{}", self.lexeme),
         }
     }
 }
 
+impl fmt::Display for TokenKind<'_> {
+    /// Prints the token as it would be written in source, so that `Equals` prints as `==`,
+    /// keywords print as the keyword and `Identifier("add")` prints as `add`. Literals are printed
+    /// with their quotes and prefixes restored and any resolved escape sequences escaped again.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            TokenKind::Char(character) => {
+                f.write_str("'")?;
+                write_escaped(f, &character.to_string(), '\'')?;
+                return f.write_str("'");
+            }
+            TokenKind::String(text) => {
+                f.write_str("\"")?;
+                write_escaped(f, text, '"')?;
+                return f.write_str("\"");
+            }
+            TokenKind::InterpolatedString(parts) => {
+                f.write_str("\"")?;
+                for part in parts {
+                    match part {
+                        StringPart::Text(text) => write_escaped(f, text, '"')?,
+                        StringPart::Expression(expression) => write!(f, "{{{expression}}}")?,
+                    }
+                }
+                return f.write_str("\"");
+            }
+            TokenKind::RadixNumber(radix, digits) => {
+                let prefix = match radix {
+                    Radix::Binary => "0b",
+                    Radix::Octal => "0o",
+                    Radix::Hexadecimal => "0x",
+                };
+                return write!(f, "{prefix}{digits}");
+            }
+            TokenKind::Comment(comment) => return write!(f, "//{comment}"),
+            TokenKind::BlockComment(comment) => return write!(f, "/*{comment}*/"),
+            TokenKind::DocComment(doc) => return write!(f, "///{doc}"),
+            TokenKind::Error(kind) => return write!(f, "{kind}"),
+
+            TokenKind::Number(text)
+            | TokenKind::Float(text)
+            | TokenKind::Keyword(text)
+            | TokenKind::Identifier(text)
+            | TokenKind::Whitespace(text) => text,
+
+            TokenKind::LeftParen => "(",
+            TokenKind::RightParen => ")",
+            TokenKind::LeftBracket => "[",
+            TokenKind::RightBracket => "]",
+            TokenKind::LeftBrace => "{",
+            TokenKind::RightBrace => "}",
+            TokenKind::Semicolon => ";",
+            TokenKind::Comma => ",",
+            TokenKind::Dot => ".",
+            TokenKind::DotDot => "..",
+            TokenKind::Colon => ":",
+            TokenKind::DoubleColon => "::",
+            TokenKind::Arrow => "->",
+            TokenKind::FatArrow => "=>",
+
+            TokenKind::Ampersand => "&",
+            TokenKind::Asperand => "@",
+            TokenKind::Carrot => "^",
+            TokenKind::Dollar => "$",
+            TokenKind::Pipe => "|",
+            TokenKind::Pound => "#",
+            TokenKind::Tilde => "~",
+
+            TokenKind::And => "&&",
+            TokenKind::Assignment => "=",
+            TokenKind::Asterisk => "*",
+            TokenKind::AsteriskAssignment => "*=",
+            TokenKind::Bang => "!",
+            TokenKind::Equals => "==",
+            TokenKind::GreaterThan => ">",
+            TokenKind::GreaterThanOrEqual => ">=",
+            TokenKind::LessThan => "<",
+            TokenKind::LessThanOrEqual => "<=",
+            TokenKind::Minus => "-",
+            TokenKind::MinusAssignment => "-=",
+            TokenKind::NotEquals => "!=",
+            TokenKind::Or => "||",
+            TokenKind::Percent => "%",
+            TokenKind::PercentAssignment => "%=",
+            TokenKind::Plus => "+",
+            TokenKind::PlusAssignment => "+=",
+            TokenKind::Slash => "/",
+            TokenKind::SlashAssignment => "/=",
+
+            TokenKind::Define => "define",
+            TokenKind::Function => "func",
+            TokenKind::Let => "let",
+            TokenKind::True => "true",
+            TokenKind::False => "false",
+            TokenKind::If => "if",
+            TokenKind::Else => "else",
+            TokenKind::Return => "return",
+            TokenKind::While => "while",
+            TokenKind::For => "for",
+            TokenKind::In => "in",
+            TokenKind::Break => "break",
+            TokenKind::Continue => "continue",
+            TokenKind::Null => "null",
+            TokenKind::Const => "const",
+
+            TokenKind::EOF => "EOF",
+        };
+
+        f.write_str(text)
+    }
+}
+
+/// Writes `text` as the inside of a literal delimited by `quote`, turning every character the
+/// lexer would have read as an escape sequence back into one.
+fn write_escaped(f: &mut fmt::Formatter<'_>, text: &str, quote: char) -> fmt::Result {
+    for character in text.chars() {
+        match character {
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            '\r' => f.write_str("\\r")?,
+            '\0' => f.write_str("\\0")?,
+            '\\' => f.write_str("\\\\")?,
+            '{' | '}' if quote == '"' => write!(f, "\\{character}")?,
+            _ if character == quote => write!(f, "\\{character}")?,
+            _ => write!(f, "{character}")?,
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use crate::lexer::Lexer;
+    use crate::token::{Precedence, Radix, StringPart, TokenKind};
+
+    #[test]
+    fn test_classification() {
+        assert!(TokenKind::Plus.is_operator());
+        assert!(TokenKind::SlashAssignment.is_operator());
+        assert!(!TokenKind::Comma.is_operator());
+        assert!(TokenKind::While.is_keyword());
+        assert!(TokenKind::Keyword("unless".into()).is_keyword());
+        assert!(!TokenKind::Identifier("while_".into()).is_keyword());
+        assert!(TokenKind::Float("1.5".into()).is_literal());
+        assert!(TokenKind::True.is_literal() && TokenKind::True.is_keyword());
+        assert!(!TokenKind::Identifier("x".into()).is_literal());
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert!(TokenKind::Asterisk.precedence() == Some(Precedence::Product));
+        assert!(TokenKind::Minus.precedence() == Some(Precedence::Sum));
+        assert!(TokenKind::LessThanOrEqual.precedence() == Some(Precedence::LessGreater));
+        assert!(TokenKind::LeftParen.precedence() == Some(Precedence::Call));
+        assert!(TokenKind::Assignment.precedence().is_none());
+        assert!(TokenKind::Semicolon.precedence().is_none());
+        assert!(TokenKind::Or.precedence() < TokenKind::And.precedence());
+        assert!(TokenKind::And.precedence() < TokenKind::Equals.precedence());
+        assert!(Precedence::Product < Precedence::Prefix);
+        assert!(Precedence::Call < Precedence::Index);
+    }
+
+    #[test]
+    fn test_display_symbols_and_keywords() {
+        assert!(TokenKind::Equals.to_string() == "==");
+        assert!(TokenKind::RightParen.to_string() == ")");
+        assert!(TokenKind::Function.to_string() == "func");
+        assert!(TokenKind::Identifier("add".into()).to_string() == "add");
+        assert!(TokenKind::EOF.to_string() == "EOF");
+    }
+
+    #[test]
+    fn test_display_literals() {
+        let interpolated = TokenKind::InterpolatedString(vec![
+            StringPart::Text("hi \"".into()),
+            StringPart::Expression("name".into()),
+            StringPart::Text("\n".into()),
+        ]);
+
+        assert!(TokenKind::String("a\tb{c}".into()).to_string() == r#""a\tb\{c\}""#);
+        assert!(interpolated.to_string() == r#""hi \"{name}\n""#);
+        assert!(TokenKind::Char('\'').to_string() == r"'\''");
+        assert!(TokenKind::RadixNumber(Radix::Hexadecimal, "FF".into()).to_string() == "0xFF");
+        assert!(TokenKind::Float("1.5e3".into()).to_string() == "1.5e3");
+    }
+
+    #[test]
+    fn test_display_comments() {
+        assert!(TokenKind::Comment(" note".into()).to_string() == "// note");
+        assert!(TokenKind::BlockComment(" a\nb ".into()).to_string() == "/* a\nb */");
+        assert!(TokenKind::DocComment(" Adds.".into()).to_string() == "/// Adds.");
+    }
+
+    #[test]
+    fn test_display_token_as_written() {
+        let tokens: Vec<String> = Lexer::new("x = 0xF_F;")
+            .unwrap()
+            .map(|token| token.to_string())
+            .collect();
+
+        assert!(tokens == vec!["x", "=", "0xF_F", ";", "EOF"]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        use crate::lexer::LexErrorKind;
+        use crate::span::Span;
+        use crate::token::Token;
+
+        let kinds = vec![
+            TokenKind::Char('\n'),
+            TokenKind::String("say \"hi\"".into()),
+            TokenKind::InterpolatedString(vec![
+                StringPart::Text("hello ".into()),
+                StringPart::Expression("name".into()),
+            ]),
+            TokenKind::Number("1000".into()),
+            TokenKind::Float("1.5e3".into()),
+            TokenKind::RadixNumber(Radix::Binary, "101".into()),
+            TokenKind::RadixNumber(Radix::Octal, "17".into()),
+            TokenKind::RadixNumber(Radix::Hexadecimal, "FF".into()),
+            TokenKind::LeftParen,
+            TokenKind::RightParen,
+            TokenKind::RightBracket,
+            TokenKind::LeftBracket,
+            TokenKind::RightBrace,
+            TokenKind::LeftBrace,
+            TokenKind::Semicolon,
+            TokenKind::Comma,
+            TokenKind::Dot,
+            TokenKind::DotDot,
+            TokenKind::Colon,
+            TokenKind::DoubleColon,
+            TokenKind::Arrow,
+            TokenKind::FatArrow,
+            TokenKind::Ampersand,
+            TokenKind::Asperand,
+            TokenKind::Carrot,
+            TokenKind::Dollar,
+            TokenKind::Pipe,
+            TokenKind::Pound,
+            TokenKind::Tilde,
+            TokenKind::And,
+            TokenKind::Assignment,
+            TokenKind::Asterisk,
+            TokenKind::AsteriskAssignment,
+            TokenKind::Bang,
+            TokenKind::Equals,
+            TokenKind::GreaterThan,
+            TokenKind::GreaterThanOrEqual,
+            TokenKind::LessThan,
+            TokenKind::LessThanOrEqual,
+            TokenKind::Minus,
+            TokenKind::MinusAssignment,
+            TokenKind::NotEquals,
+            TokenKind::Or,
+            TokenKind::Percent,
+            TokenKind::PercentAssignment,
+            TokenKind::Plus,
+            TokenKind::PlusAssignment,
+            TokenKind::Slash,
+            TokenKind::SlashAssignment,
+            TokenKind::Define,
+            TokenKind::Function,
+            TokenKind::Let,
+            TokenKind::True,
+            TokenKind::False,
+            TokenKind::If,
+            TokenKind::Else,
+            TokenKind::Return,
+            TokenKind::While,
+            TokenKind::For,
+            TokenKind::In,
+            TokenKind::Break,
+            TokenKind::Continue,
+            TokenKind::Null,
+            TokenKind::Const,
+            TokenKind::Keyword("unless".into()),
+            TokenKind::Identifier("add".into()),
+            TokenKind::Error(LexErrorKind::IllegalCharacter),
+            TokenKind::Whitespace(" \t".into()),
+            TokenKind::Comment(" note".into()),
+            TokenKind::BlockComment(" a\nb ".into()),
+            TokenKind::DocComment(" Adds two numbers.".into()),
+            TokenKind::EOF,
+        ];
+
+        for kind in kinds {
+            let lexeme = kind.to_string();
+            let token = Token {
+                span: Span {
+                    start: 3,
+                    end: 3 + lexeme.len(),
+                    line: 1,
+                    column: 4,
+                },
+                lexeme: lexeme.into(),
+                kind,
+            };
+
+            let json = serde_json::to_string(&token).unwrap();
+            let actual: Token = serde_json::from_str(&json).unwrap();
+            println!("ACTUAL=`{actual:?}`, EXPECTED=`{token:?}`");
+            assert!(actual == token);
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use plain::keyword::KeywordTable;
+use plain::lexer::Lexer;
+use plain::token::TokenKind;
+
+#[test]
+fn test_lex_through_library() {
+    const INPUT: &str = "unless x >= 10;";
+
+    let keywords = KeywordTable::new().with_keyword("unless", TokenKind::If);
+    let tokens: Vec<TokenKind> = Lexer::new(INPUT)
+        .unwrap()
+        .with_keywords(keywords)
+        .map(|token| token.kind)
+        .collect();
+
+    assert!(
+        tokens
+            == vec![
+                TokenKind::If,
+                TokenKind::Identifier("x".into()),
+                TokenKind::GreaterThanOrEqual,
+                TokenKind::Number("10".into()),
+                TokenKind::Semicolon,
+                TokenKind::EOF,
+            ]
+    );
+}